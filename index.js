@@ -1 +1,28 @@
-import('./pkg').catch(console.error);
+// index.html?players=2 shows a second board next to the first one
+const players = new URLSearchParams(window.location.search).get('players') === '2' ? 2 : 1;
+
+// copies the first board, with all ids and label references prefixed
+function addBoard(prefix) {
+    const board = document.getElementById('board').cloneNode(true);
+    board.id = prefix + board.id;
+    for (const element of board.querySelectorAll('[id]')) {
+        element.id = prefix + element.id;
+    }
+    for (const label of board.querySelectorAll('label[for]')) {
+        label.htmlFor = prefix + label.htmlFor;
+    }
+    document.getElementById('boards').appendChild(board);
+}
+
+if (players === 2) {
+    addBoard('p2-');
+}
+
+import('./pkg')
+    .then((blockstack) => {
+        new blockstack.GameHandle('');
+        if (players === 2) {
+            new blockstack.GameHandle('p2-');
+        }
+    })
+    .catch(console.error);
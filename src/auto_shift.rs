@@ -0,0 +1,143 @@
+// Repeated sideways movement while a direction is held, independent of the key repeat of the OS.
+// After the first move, the block waits `das` ms (delayed auto shift),
+// then moves one cell every `arr` ms (auto repeat rate), or straight to the wall if `arr` is 0.
+// All times are game time in ms.
+#[derive(Clone)]
+pub struct AutoShift {
+    das: u64,
+    arr: u64,
+    left_held: bool,
+    right_held: bool,
+    // -1 for left, 1 for right, 0 if neither is held
+    direction: i32,
+    // when the direction started being held
+    press_time: u64,
+    // repeated moves done since the delay ran out
+    repeats: u64,
+}
+
+impl AutoShift {
+    pub const fn new(das: u32, arr: u32) -> Self {
+        Self {
+            das: das as u64,
+            arr: arr as u64,
+            left_held: false,
+            right_held: false,
+            direction: 0,
+            press_time: 0,
+            repeats: 0,
+        }
+    }
+
+    // the more recently pressed direction wins
+    pub fn press(&mut self, direction: i32, time: u64) {
+        self.set_held(direction, true);
+        self.start(direction, time);
+    }
+
+    // if the other direction is still held, it takes over and has to charge again
+    pub fn release(&mut self, direction: i32, time: u64) {
+        self.set_held(direction, false);
+        if self.direction == direction {
+            if self.is_held(-direction) {
+                self.start(-direction, time);
+            } else {
+                self.direction = 0;
+            }
+        }
+    }
+
+    pub fn get_direction(&self) -> i32 {
+        self.direction
+    }
+
+    // Number of cells to move in the held direction at `time`, u32::MAX for all the way.
+    // Not reset when a new block comes in, so a charged shift carries over to it.
+    pub fn update(&mut self, time: u64) -> u32 {
+        if self.direction == 0 || time < self.press_time + self.das {
+            return 0;
+        }
+        if self.arr == 0 {
+            return u32::MAX;
+        }
+        let elapsed = time - self.press_time - self.das;
+        // without a delay the press move itself counts as the first one
+        let due = if self.das == 0 {
+            elapsed / self.arr
+        } else {
+            elapsed / self.arr + 1
+        };
+        let shifts = due - self.repeats;
+        self.repeats = due;
+        shifts as u32
+    }
+
+    fn start(&mut self, direction: i32, time: u64) {
+        self.direction = direction;
+        self.press_time = time;
+        self.repeats = 0;
+    }
+
+    fn set_held(&mut self, direction: i32, held: bool) {
+        if direction < 0 {
+            self.left_held = held;
+        } else {
+            self.right_held = held;
+        }
+    }
+
+    fn is_held(&self, direction: i32) -> bool {
+        if direction < 0 {
+            self.left_held
+        } else {
+            self.right_held
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_start_after_the_delay_at_the_repeat_rate() {
+        let mut auto_shift = AutoShift::new(150, 50);
+        auto_shift.press(1, 1000);
+        assert_eq!(auto_shift.update(1149), 0);
+        assert_eq!(auto_shift.update(1150), 1);
+        assert_eq!(auto_shift.update(1199), 0);
+        assert_eq!(auto_shift.update(1300), 3);
+    }
+
+    #[test]
+    fn zero_delay_repeats_at_the_repeat_rate_after_the_press_move() {
+        let mut auto_shift = AutoShift::new(0, 50);
+        auto_shift.press(1, 1000);
+        assert_eq!(auto_shift.update(1000), 0);
+        assert_eq!(auto_shift.update(1049), 0);
+        assert_eq!(auto_shift.update(1050), 1);
+        assert_eq!(auto_shift.update(1200), 3);
+    }
+
+    #[test]
+    fn zero_repeat_rate_moves_all_the_way() {
+        let mut auto_shift = AutoShift::new(100, 0);
+        auto_shift.press(-1, 0);
+        assert_eq!(auto_shift.update(100), u32::MAX);
+    }
+
+    #[test]
+    fn releasing_the_newer_direction_recharges_the_older_one() {
+        let mut auto_shift = AutoShift::new(100, 20);
+        auto_shift.press(-1, 0);
+        auto_shift.press(1, 50);
+        assert_eq!(auto_shift.get_direction(), 1);
+        auto_shift.release(1, 200);
+        assert_eq!(auto_shift.get_direction(), -1);
+        assert_eq!(auto_shift.update(250), 0);
+        assert_eq!(auto_shift.update(300), 1);
+        auto_shift.release(-1, 310);
+        assert_eq!(auto_shift.get_direction(), 0);
+        assert_eq!(auto_shift.update(1000), 0);
+    }
+}
@@ -1,133 +1,253 @@
 use crate::blocks;
+use crate::colours;
 use crate::objects::Point;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
 
+// colours of a single landed piece
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Cell {
+    #[serde(with = "colours::static_colour")]
+    pub colour_1: colours::Colour,
+    #[serde(with = "colours::static_colour")]
+    pub colour_2: colours::Colour,
+}
+
+// Landed pieces, stored as a fixed grid of cells in row-major order.
+// Occupancy queries are a single index lookup.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BlockStack {
-    block_stack: HashMap<i32, Vec<blocks::Block>>,
+    width: i32,
+    height: i32,
+    cells: Vec<Option<Cell>>,
+    // number of occupied cells per row, a row is complete when it reaches `width`
+    row_fill: Vec<i32>,
 }
 
 impl BlockStack {
-    pub fn new() -> Self {
+    pub fn new(width: i32, height: i32) -> Self {
         Self {
-            block_stack: HashMap::new(),
+            width,
+            height,
+            cells: vec![None; (width * height) as usize],
+            row_fill: vec![0; height as usize],
         }
     }
 
-    pub fn get_stack(&self) -> &HashMap<i32, Vec<blocks::Block>> {
-        &self.block_stack
+    // false if the stack came from an inconsistent snapshot
+    pub fn is_valid(&self) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.cells.len() == (self.width * self.height) as usize
+            && self.row_fill.len() == self.height as usize
+            && self
+                .cells
+                .chunks(self.width as usize)
+                .zip(&self.row_fill)
+                .all(|(row, fill)| row.iter().flatten().count() as i32 == *fill)
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+
+    pub fn get_cell(&self, x: i32, y: i32) -> Option<&Cell> {
+        self.index(x, y)
+            .and_then(|index| self.cells[index].as_ref())
+    }
+
+    pub fn is_occupied(&self, x: i32, y: i32) -> bool {
+        self.get_cell(x, y).is_some()
     }
 
-    pub fn check_collision(&self, piece: &Point) -> bool {
-        let mut piece_ok = true;
-        'outer: for (_index, row) in &self.block_stack {
-            for stack_block in row {
-                for stack_point in stack_block.get_pieces() {
-                    if piece.x == stack_point.x && piece.y == stack_point.y {
-                        piece_ok = false;
-                        break 'outer;
-                    }
+    pub fn is_empty(&self) -> bool {
+        self.row_fill.iter().all(|fill| *fill == 0)
+    }
+
+    // all occupied cells with their position
+    pub fn get_cells(&self) -> impl Iterator<Item = (Point, &Cell)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, cell)| {
+                cell.as_ref().map(|cell| {
+                    let point = Point {
+                        x: index as i32 % width,
+                        y: index as i32 / width,
+                    };
+                    (point, cell)
+                })
+            })
+    }
+
+    // true if the cell is on the board and not occupied
+    pub fn is_free(&self, point: &Point) -> bool {
+        self.index(point.x, point.y)
+            .is_some_and(|index| self.cells[index].is_none())
+    }
+
+    // true if every piece of the block is on the board and not occupied.
+    // The one check for moving, rotating, dropping and spawning blocks.
+    pub fn fits(&self, block: &blocks::Block) -> bool {
+        block.get_pieces().iter().all(|point| self.is_free(point))
+    }
+
+    // number of rows the block can fall before it lands on the stack or the floor
+    pub fn get_drop_distance(&self, block: &blocks::Block) -> i32 {
+        if block.get_pieces().is_empty() {
+            // nothing in play, e.g. during the entry delay
+            return 0;
+        }
+        let mut distance = 0;
+        while self.fits(&block.get_moved(0, distance + 1)) {
+            distance += 1;
+        }
+        distance
+    }
+
+    pub fn add_block_to_stack(&mut self, block: &blocks::Block) {
+        let cell = Cell {
+            colour_1: block.get_colour_1(),
+            colour_2: block.get_colour_2(),
+        };
+        for point in block.get_pieces() {
+            if let Some(index) = self.index(point.x, point.y) {
+                if self.cells[index].is_none() {
+                    self.row_fill[point.y as usize] += 1;
                 }
+                self.cells[index] = Some(cell);
             }
         }
-        return piece_ok;
     }
 
-    pub fn add_block_to_stack(&mut self, block: &blocks::Block) {
-        let block_stack = &mut self.block_stack;
-        let stack_blocks = blocks::stack_blocks(&block);
-        for block in stack_blocks {
-            let index = block.get_pieces().get(0).unwrap().y;
-            let row_opt = block_stack.get_mut(&index);
-            let row: &mut Vec<blocks::Block>;
-            if row_opt.is_none() {
-                let tmp_row = Vec::new();
-                block_stack.insert(index, tmp_row);
-                // get row back from map directly after inserting it
-                // to get lifetime right
-                row = block_stack.get_mut(&index).unwrap();
-            } else {
-                row = row_opt.unwrap();
+    // Stack drawn as text for tests, one string per row from the top, '#' for an occupied cell.
+    #[cfg(test)]
+    pub(crate) fn from_rows(rows: &[&str]) -> Self {
+        let mut stack = BlockStack::new(rows[0].len() as i32, rows.len() as i32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                if cell == '#' {
+                    stack.cells[y * stack.width as usize + x] = Some(Cell {
+                        colour_1: colours::NO_COLOUR,
+                        colour_2: colours::NO_COLOUR,
+                    });
+                    stack.row_fill[y] += 1;
+                }
             }
-            row.push(block);
         }
+        stack
     }
 
-    pub fn reduce_stack(&mut self, game_width: i32) -> usize {
-        let complete_row_indexes = self.find_complete_row_indexes(game_width);
-        self.remove_completed_rows(&complete_row_indexes);
-        complete_row_indexes.len()
+    // rows that are completely filled, top down
+    pub fn get_complete_rows(&self) -> Vec<i32> {
+        (0..self.height)
+            .filter(|row| self.row_fill[*row as usize] == self.width)
+            .collect()
     }
 
-    fn find_complete_row_indexes(&self, game_width: i32) -> HashSet<i32> {
-        let mut complete_row_indexes: HashSet<i32> = HashSet::new();
-        for (index, row) in &self.block_stack {
-            let mut xs_in_row = HashSet::new();
-            for block in row {
-                let x = block.get_pieces().get(0).unwrap().x;
-                xs_in_row.insert(x);
-            }
-            let mut row_complete = true;
-            let mut i = 0;
-            while i < game_width {
-                let present = xs_in_row.get(&i);
-                i = i + 1;
-                if present.is_none() {
-                    row_complete = false;
-                    break;
-                }
+    pub fn reduce_stack(&mut self) -> usize {
+        let width = self.width as usize;
+        let mut removed = 0;
+        // compact rows bottom up, copying each kept row down by the number of complete rows below it
+        let mut target_row = self.height as usize;
+        for row in (0..self.height as usize).rev() {
+            if self.row_fill[row] == self.width {
+                removed += 1;
+                continue;
             }
-            if row_complete {
-                complete_row_indexes.insert(*index);
+            target_row -= 1;
+            if target_row != row {
+                self.cells
+                    .copy_within(row * width..(row + 1) * width, target_row * width);
+                self.row_fill[target_row] = self.row_fill[row];
             }
         }
-        complete_row_indexes
+        // rows at the top that were shifted down are empty now
+        for row in 0..target_row {
+            self.cells[row * width..(row + 1) * width].fill(None);
+            self.row_fill[row] = 0;
+        }
+        removed
     }
+}
 
-    fn remove_completed_rows(&mut self, complete_row_indexes: &HashSet<i32>) {
-        if !complete_row_indexes.is_empty() {
-            // find out which rows need to be shifed by which amount
-            let mut row_idx_to_be_shifted_by_count: HashMap<i32, i32> = HashMap::new();
-            for old_index in self.block_stack.keys() {
-                if complete_row_indexes.contains(old_index) {
-                    // row will be removed
-                    continue;
-                }
-                let mut to_be_shifted_by_rows = 0;
-                for idx_to_be_removed in complete_row_indexes {
-                    if *old_index < *idx_to_be_removed {
-                        to_be_shifted_by_rows = to_be_shifted_by_rows + 1;
-                    }
-                }
-                row_idx_to_be_shifted_by_count.insert(*old_index, to_be_shifted_by_rows);
-            }
-            // build map of old to new index
-            let mut old_idx_to_new: HashMap<i32, i32> = HashMap::new();
-            for old_index in self.block_stack.keys() {
-                let to_by_shifted_by_opt = row_idx_to_be_shifted_by_count.get(old_index);
-                if to_by_shifted_by_opt.is_some() {
-                    let to_by_shifted_by = to_by_shifted_by_opt.unwrap();
-                    let new_index = old_index + to_by_shifted_by;
-                    old_idx_to_new.insert(*old_index, new_index);
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CELL: Cell = Cell {
+        colour_1: "#00F",
+        colour_2: "#009",
+    };
+
+    fn fill_row(stack: &mut BlockStack, y: i32, gap: Option<i32>) {
+        for x in 0..stack.get_width() {
+            if Some(x) != gap {
+                let index = stack.index(x, y).unwrap();
+                stack.cells[index] = Some(CELL);
+                stack.row_fill[y as usize] += 1;
             }
-            // build new stack
-            let mut reduced_stack: HashMap<i32, Vec<blocks::Block>> = HashMap::new();
-            for old_idx in old_idx_to_new.keys() {
-                let new_idx = old_idx_to_new.get(old_idx).unwrap();
-                let row_opt = self.block_stack.remove(old_idx);
-                let row = row_opt.unwrap();
-                reduced_stack.insert(*new_idx, row);
-                // adjust y of all points
-                let row_opt2 = reduced_stack.get_mut(new_idx);
-                let row_mut = row_opt2.unwrap();
-                for block in row_mut {
-                    for point in block.get_pieces_mut() {
-                        point.y = *new_idx;
-                    }
-                }
+        }
+    }
+
+    #[test]
+    fn reduce_stack_removes_complete_rows_and_shifts_rows_above_down() {
+        let mut stack = BlockStack::new(4, 6);
+        fill_row(&mut stack, 5, None);
+        fill_row(&mut stack, 4, Some(1));
+        fill_row(&mut stack, 3, None);
+        fill_row(&mut stack, 2, Some(3));
+
+        assert_eq!(stack.reduce_stack(), 2);
+
+        assert!(!stack.is_occupied(1, 5));
+        assert!(stack.is_occupied(0, 5));
+        assert!(!stack.is_occupied(3, 4));
+        assert!(stack.is_occupied(0, 4));
+        for y in 0..4 {
+            for x in 0..4 {
+                assert!(!stack.is_occupied(x, y));
             }
-            self.block_stack = reduced_stack;
         }
+        assert_eq!(stack.get_cells().count(), 6);
+    }
+
+    #[test]
+    fn clearing_all_rows_leaves_an_empty_stack() {
+        let mut stack = BlockStack::new(3, 4);
+        fill_row(&mut stack, 3, None);
+        fill_row(&mut stack, 2, None);
+        assert!(!stack.is_empty());
+
+        assert_eq!(stack.reduce_stack(), 2);
+        assert!(stack.is_empty());
+        assert!(stack.is_free(&Point { x: 0, y: 3 }));
+    }
+
+    #[test]
+    fn reduce_stack_works_on_boards_larger_than_the_default() {
+        let mut stack = BlockStack::new(40, 45);
+        fill_row(&mut stack, 44, None);
+        fill_row(&mut stack, 43, Some(39));
+        fill_row(&mut stack, 42, None);
+
+        assert_eq!(stack.reduce_stack(), 2);
+
+        assert!(stack.is_occupied(38, 44));
+        assert!(!stack.is_occupied(39, 44));
+        assert!(!stack.is_occupied(0, 43));
+        assert_eq!(stack.get_cells().count(), 39);
     }
 }
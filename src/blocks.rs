@@ -1,11 +1,11 @@
 use crate::colours;
-use crate::game;
 use crate::objects::Point;
+use crate::rotation;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum BlockType {
     DefaultBlock,
-    StackBlock,
     Long,
     Tee,
     Quad,
@@ -15,12 +15,33 @@ pub enum BlockType {
     LRight,
 }
 
-#[derive(Clone)]
+impl BlockType {
+    // block types that can be dealt to the player, in the order of `rand_for_block`
+    pub const PLAYABLE: [BlockType; 7] = [
+        BlockType::Long,
+        BlockType::Tee,
+        BlockType::Quad,
+        BlockType::StepLeft,
+        BlockType::StepRight,
+        BlockType::LLeft,
+        BlockType::LRight,
+    ];
+}
+
+pub const NUM_BLOCK_TYPES: i32 = BlockType::PLAYABLE.len() as i32;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Block {
     pieces: Vec<Point>,
-    colour_1: &'static str,
-    colour_2: &'static str,
+    #[serde(with = "colours::static_colour")]
+    colour_1: colours::Colour,
+    #[serde(with = "colours::static_colour")]
+    colour_2: colours::Colour,
     block_type: BlockType,
+    // upper left corner of the bounding box the pieces are placed in
+    position: Point,
+    // index into the rotation states of `rotation::get_shape`
+    rotation: usize,
 }
 
 impl Block {
@@ -28,10 +49,6 @@ impl Block {
         &self.pieces
     }
 
-    pub fn get_pieces_mut(&mut self) -> &mut Vec<Point> {
-        &mut self.pieces
-    }
-
     pub fn get_colour_1(&self) -> &'static str {
         self.colour_1
     }
@@ -40,543 +57,77 @@ impl Block {
         self.colour_2
     }
 
-    pub fn apply_rotated(&mut self, rotated: &Vec<Point>) {
-        self.pieces.clear();
-        for point in rotated {
-            self.pieces.push(*point)
-        }
+    pub fn get_block_type(&self) -> BlockType {
+        self.block_type
     }
 
-    pub fn get_rotated_left(&self) -> Vec<Point> {
-        match &self.block_type {
-            BlockType::DefaultBlock => self.pieces.clone(),
-            BlockType::StackBlock => self.pieces.clone(),
-            BlockType::Long => self.get_rotated_left_long(),
-            BlockType::Tee => self.get_rotated_left_tee(),
-            BlockType::Quad => self.get_rotated_left_quad(),
-            BlockType::StepLeft => self.get_rotated_left_step_left(),
-            BlockType::StepRight => self.get_rotated_left_step_right(),
-            BlockType::LLeft => self.get_rotated_left_l_left(),
-            BlockType::LRight => self.get_rotated_left_l_right(),
-        }
-    }
-    pub fn get_rotated_right(&self) -> Vec<Point> {
-        match &self.block_type {
-            BlockType::DefaultBlock => self.pieces.clone(),
-            BlockType::StackBlock => self.pieces.clone(),
-            BlockType::Long => self.get_rotated_right_long(),
-            BlockType::Tee => self.get_rotated_right_tee(),
-            BlockType::Quad => self.get_rotated_right_quad(),
-            BlockType::StepLeft => self.get_rotated_right_step_left(),
-            BlockType::StepRight => self.get_rotated_right_step_right(),
-            BlockType::LLeft => self.get_rotated_right_l_left(),
-            BlockType::LRight => self.get_rotated_right_l_right(),
-        }
+    // row of the lowest piece, rows count downwards
+    pub fn get_lowest_row(&self) -> i32 {
+        self.pieces.iter().map(|point| point.y).max().unwrap_or(0)
     }
 
-    fn get_rotated_left_long(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let horizontal = self.pieces[0].x == rotation_point.x;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 2,
-                y: rotation_point.y,
-            });
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 2,
-            });
-        }
-        rotated
-    }
-    fn get_rotated_right_long(&self) -> Vec<Point> {
-        self.get_rotated_left_long()
+    pub fn get_rotation(&self) -> usize {
+        self.rotation
     }
-    fn get_rotated_left_tee(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let extension = self.pieces[3];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            if extension.y > rotation_point.y {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y,
-                });
-            }
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            if extension.x > rotation_point.x {
-                rotated.push(Point {
-                    x: rotation_point.x,
-                    y: rotation_point.y - 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x,
-                    y: rotation_point.y + 1,
-                });
-            }
-        }
-        rotated
-    }
-    fn get_rotated_right_tee(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let extension = self.pieces[3];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            if extension.y > rotation_point.y {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y,
-                });
-            }
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            if extension.x > rotation_point.x {
-                rotated.push(Point {
-                    x: rotation_point.x,
-                    y: rotation_point.y + 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x,
-                    y: rotation_point.y - 1,
-                });
-            }
-        }
-        rotated
-    }
-    fn get_rotated_left_quad(&self) -> Vec<Point> {
-        self.pieces.clone()
-    }
-    fn get_rotated_right_quad(&self) -> Vec<Point> {
-        self.get_rotated_left_quad()
-    }
-    fn get_rotated_left_step_left(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y - 1,
-            });
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y - 1,
-            });
-        }
-        rotated
-    }
-    fn get_rotated_right_step_left(&self) -> Vec<Point> {
-        self.get_rotated_left_step_left()
-    }
-    fn get_rotated_left_step_right(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y - 1,
-            });
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 2,
-                y: rotation_point.y - 1,
-            });
-        }
-        rotated
-    }
-    fn get_rotated_right_step_right(&self) -> Vec<Point> {
-        self.get_rotated_left_step_right()
-    }
-    fn get_rotated_left_l_left(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let extension = self.pieces[3];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            if extension.y > rotation_point.y {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y + 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y - 1,
-                });
-            }
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            if extension.x > rotation_point.x {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y - 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y + 1,
-                });
-            }
-        }
-        rotated
+
+    pub fn get_position(&self) -> Point {
+        self.position
     }
-    fn get_rotated_right_l_left(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let extension = self.pieces[3];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            if extension.y > rotation_point.y {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y - 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y + 1,
-                });
-            }
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            if extension.x > rotation_point.x {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y + 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y - 1,
-                });
-            }
+
+    // Whether the pieces are where type, rotation and position put them, e.g. for a restored block.
+    // The default block has no pieces.
+    pub fn is_valid(&self) -> bool {
+        if self.block_type == BlockType::DefaultBlock {
+            return self.pieces.is_empty() && self.rotation == 0;
         }
-        rotated
+        self.rotation < 4
+            && self.pieces == pieces_at(self.block_type, self.rotation, &self.position)
     }
-    fn get_rotated_left_l_right(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let extension = self.pieces[3];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            if extension.y > rotation_point.y {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y - 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y + 1,
-                });
-            }
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            if extension.x > rotation_point.x {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y - 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y + 1,
-                });
-            }
+
+    // same block shifted by the given number of cells
+    pub fn get_moved(&self, dx: i32, dy: i32) -> Block {
+        let mut moved = self.clone();
+        moved.position.x += dx;
+        moved.position.y += dy;
+        for point in moved.pieces.iter_mut() {
+            point.x += dx;
+            point.y += dy;
         }
-        rotated
+        moved
     }
-    fn get_rotated_right_l_right(&self) -> Vec<Point> {
-        let mut rotated = Vec::new();
-        let rotation_point = self.pieces[1];
-        let extension = self.pieces[3];
-        let horizontal = self.pieces[0].y == rotation_point.y;
-        if horizontal {
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y - 1,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y + 1,
-            });
-            if extension.y > rotation_point.y {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y + 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y - 1,
-                });
-            }
-        } else {
-            rotated.push(Point {
-                x: rotation_point.x - 1,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x,
-                y: rotation_point.y,
-            });
-            rotated.push(Point {
-                x: rotation_point.x + 1,
-                y: rotation_point.y,
-            });
-            if extension.x > rotation_point.x {
-                rotated.push(Point {
-                    x: rotation_point.x + 1,
-                    y: rotation_point.y + 1,
-                });
-            } else {
-                rotated.push(Point {
-                    x: rotation_point.x - 1,
-                    y: rotation_point.y - 1,
-                });
-            }
+
+    // same block turned in place, clockwise for positive `turns`, see `rotation::get_shape`
+    pub fn get_rotated(&self, turns: i32) -> Block {
+        let rotation = (self.rotation as i32 + turns).rem_euclid(4) as usize;
+        Block {
+            pieces: pieces_at(self.block_type, rotation, &self.position),
+            rotation,
+            ..self.clone()
         }
-        rotated
     }
 }
 
+fn pieces_at(block_type: BlockType, rotation: usize, position: &Point) -> Vec<Point> {
+    rotation::get_shape(block_type, rotation)
+        .iter()
+        .map(|(x, y)| Point {
+            x: position.x + x,
+            y: position.y + y,
+        })
+        .collect()
+}
+
 pub const fn default_block() -> Block {
     Block {
         pieces: Vec::new(),
-        colour_1: "#000;",
-        colour_2: "#000;",
+        colour_1: colours::NO_COLOUR,
+        colour_2: colours::NO_COLOUR,
         block_type: BlockType::DefaultBlock,
+        position: Point { x: 0, y: 0 },
+        rotation: 0,
     }
 }
 
-pub fn stack_blocks(block: &Block) -> Vec<Block> {
-    let mut blocks = Vec::new();
-    for point in block.get_pieces() {
-        blocks.push(Block {
-            pieces: vec![Point {
-                x: point.x,
-                y: point.y,
-            }],
-            colour_1: block.get_colour_1(),
-            colour_2: block.get_colour_2(),
-            block_type: BlockType::StackBlock,
-        });
-    }
-    blocks
-}
-
-const HAVE_WIDTH: i32 = game::GAME_WIDTH / 2;
-
 pub fn rand_for_block(block: &Block) -> i32 {
     match block.block_type {
         BlockType::Long => 0,
@@ -587,173 +138,38 @@ pub fn rand_for_block(block: &Block) -> i32 {
         BlockType::LLeft => 5,
         BlockType::LRight => 6,
         BlockType::DefaultBlock => -1,
-        BlockType::StackBlock => -1,
     }
 }
 
-pub fn new(rand: i32, level: i32) -> Block {
+// `spawn` is the position of the block's centre column on its upper row
+pub fn new(rand: i32, level: i32, spawn: &Point) -> Block {
     let colours = colours::colours_for_level(level);
-    match rand {
-        0 => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 2,
-                    y: 0,
-                },
-            ],
-            colour_1: colours.colour_1,
-            colour_2: colours.colour_2,
-            block_type: BlockType::Long,
-        },
-        1 => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 1,
-                },
-            ],
-            colour_1: colours.colour_1,
-            colour_2: colours.colour_2,
-            block_type: BlockType::Tee,
-        },
-        2 => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 1,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 1,
-                },
-            ],
-            colour_1: colours.colour_2,
-            colour_2: colours.colour_1,
-            block_type: BlockType::Quad,
-        },
-        3 => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 1,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 1,
-                },
-            ],
-            colour_1: colours.colour_1,
-            colour_2: colours.colour_1,
-            block_type: BlockType::StepLeft,
-        },
-        4 => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 1,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 1,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 0,
-                },
-            ],
-            colour_1: colours.colour_2,
-            colour_2: colours.colour_2,
-            block_type: BlockType::StepRight,
-        },
-        5 => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 1,
-                },
-            ],
-            colour_1: colours.colour_1,
-            colour_2: colours.colour_1,
-            block_type: BlockType::LLeft,
-        },
-        _ => Block {
-            pieces: vec![
-                Point {
-                    x: HAVE_WIDTH - 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 0,
-                },
-                Point {
-                    x: HAVE_WIDTH + 1,
-                    y: 1,
-                },
-            ],
-            colour_1: colours.colour_2,
-            colour_2: colours.colour_2,
-            block_type: BlockType::LRight,
-        },
+    let (block_type, colour_1, colour_2) = match rand {
+        0 => (BlockType::Long, colours.colour_1, colours.colour_2),
+        1 => (BlockType::Tee, colours.colour_1, colours.colour_2),
+        2 => (BlockType::Quad, colours.colour_2, colours.colour_1),
+        3 => (BlockType::StepLeft, colours.colour_1, colours.colour_1),
+        4 => (BlockType::StepRight, colours.colour_2, colours.colour_2),
+        5 => (BlockType::LLeft, colours.colour_1, colours.colour_1),
+        _ => (BlockType::LRight, colours.colour_2, colours.colour_2),
+    };
+    let rotation = rotation::get_spawn_rotation(block_type);
+    // move the bounding box so the upper row of the spawn state is at `spawn`
+    let top = rotation::get_shape(block_type, rotation)
+        .iter()
+        .map(|(_x, y)| *y)
+        .min()
+        .unwrap();
+    let position = Point {
+        x: spawn.x - 1,
+        y: spawn.y - top,
+    };
+    Block {
+        pieces: pieces_at(block_type, rotation, &position),
+        colour_1,
+        colour_2,
+        block_type,
+        position,
+        rotation,
     }
 }
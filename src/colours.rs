@@ -1,11 +1,19 @@
+// Colours are always one of the static colours in this file.
+// Fields use the alias so that the serde derive does not try to borrow them from the input.
+pub type Colour = &'static str;
+
+// colour of blocks that are not shown
+pub const NO_COLOUR: &str = "#000;";
+
 pub struct Colours {
     pub colour_1: &'static str,
     pub colour_2: &'static str,
     pub colour_bg: &'static str,
 }
 
+// level 1 has the first colours, they repeat every 10 levels
 pub fn colours_for_level(level: i32) -> &'static Colours {
-    &COLOURS[level as usize % COLOURS.len() - 1]
+    &COLOURS[(level - 1).rem_euclid(COLOURS.len() as i32) as usize]
 }
 
 pub const COLOURS: [Colours; 10] = [
@@ -60,3 +68,48 @@ pub const COLOURS: [Colours; 10] = [
         colour_bg: "#999",
     },
 ];
+
+// the `&'static str` a colour name refers to, if it is one of the colours above
+pub fn find_colour(colour: &str) -> Option<&'static str> {
+    COLOURS
+        .iter()
+        .flat_map(|colours| [colours.colour_1, colours.colour_2, colours.colour_bg])
+        .chain([NO_COLOUR])
+        .find(|known| *known == colour)
+}
+
+// serde support for colour fields, which refer to the static colours above
+pub mod static_colour {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        colour: &super::Colour,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(colour)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<super::Colour, D::Error> {
+        let colour = String::deserialize(deserializer)?;
+        super::find_colour(&colour)
+            .ok_or_else(|| D::Error::custom(format!("unknown colour {}", colour)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_level_has_colours() {
+        for level in 0..=40 {
+            colours_for_level(level);
+        }
+        assert_eq!(colours_for_level(1).colour_1, COLOURS[0].colour_1);
+        assert_eq!(colours_for_level(11).colour_1, COLOURS[0].colour_1);
+        assert_eq!(colours_for_level(10).colour_1, COLOURS[9].colour_1);
+    }
+}
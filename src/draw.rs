@@ -1,91 +1,98 @@
 use crate::blocks::Block;
+use crate::game::GameConfig;
 use crate::objects::DrawState;
 use crate::objects::Point;
-
-use wasm_bindgen::prelude::*;
+use crate::render::Renderer;
+use crate::t_spin::TSpin;
 
 pub const BLOCK_SIZE: i32 = 15;
 const SUB_BLOCK_SIZE: i32 = 3;
+// size of the preview and hold canvas in blocks, the preview has one slot per queued block
+const PREVIEW_WIDTH: i32 = 6;
+const PREVIEW_SLOT_HEIGHT: i32 = 3;
+// opacity of the ghost block, in the colours of the current block
+const GHOST_ALPHA: f64 = 0.3;
+// number of frames a callout like "T-spin double" stays on the board
+const CALLOUT_FRAMES: u32 = 90;
 
 #[derive(PartialEq)]
 enum DrawMode {
-    GAME,
-    PREVIEW,
+    Game,
+    // position in the queue, 0 for the next block
+    Preview { slot: i32 },
+    Hold,
 }
 
+// Renderer drawing onto the game, preview and hold canvas.
 pub struct Draw {
-    context: Option<web_sys::CanvasRenderingContext2d>,
-    context_next: Option<web_sys::CanvasRenderingContext2d>,
+    context: web_sys::CanvasRenderingContext2d,
+    context_next: web_sys::CanvasRenderingContext2d,
+    context_hold: web_sys::CanvasRenderingContext2d,
     width: i32,
     height: i32,
+    hidden_rows: i32,
+    spawn_point: Point,
+    // text shown over the board and the frames left to show it
+    callout: Option<(String, u32)>,
 }
 impl Draw {
-    pub const fn create(
-        context: Option<web_sys::CanvasRenderingContext2d>,
-        context_next: Option<web_sys::CanvasRenderingContext2d>,
-        width: i32,
-        height: i32,
+    pub fn create(
+        context: web_sys::CanvasRenderingContext2d,
+        context_next: web_sys::CanvasRenderingContext2d,
+        context_hold: web_sys::CanvasRenderingContext2d,
     ) -> Self {
         Self {
-            context: context,
-            context_next: context_next,
-            width: width * BLOCK_SIZE,
-            height: height * BLOCK_SIZE,
+            context,
+            context_next,
+            context_hold,
+            width: 0,
+            height: 0,
+            hidden_rows: 0,
+            spawn_point: Point { x: 0, y: 0 },
+            callout: None,
         }
     }
 
     fn calc_coord(&self, point: &Point, mode: &DrawMode) -> Point {
         match mode {
-            DrawMode::GAME => Point {
+            // hidden rows are above the canvas
+            DrawMode::Game => Point {
                 x: point.x * BLOCK_SIZE,
-                y: point.y * BLOCK_SIZE,
+                y: (point.y - self.hidden_rows) * BLOCK_SIZE,
             },
-            DrawMode::PREVIEW => Point {
-                x: (point.x - 4) * BLOCK_SIZE,
-                y: (point.y + 4) * BLOCK_SIZE,
+            // blocks in the preview and hold are still at their spawn point
+            DrawMode::Preview { slot } => Point {
+                x: (point.x - self.spawn_point.x + 2) * BLOCK_SIZE,
+                y: (point.y - self.spawn_point.y + 1 + slot * PREVIEW_SLOT_HEIGHT) * BLOCK_SIZE,
             },
+            DrawMode::Hold => self.calc_coord(point, &DrawMode::Preview { slot: 0 }),
         }
     }
 
-    pub fn draw_pause(&self) {
-        let context: &web_sys::CanvasRenderingContext2d = self.context.as_ref().unwrap();
-        context.set_font("bold 30px serif");
-        context.set_text_align("center");
-        context.set_fill_style(&JsValue::from_str("#00F"));
-        let result = context.fill_text("PAUSE", (self.width / 2).into(), (self.height / 2).into());
-        crate::utils::handle_js_error(result);
+    fn draw_clear(&self, context: &web_sys::CanvasRenderingContext2d) {
+        // preview and game canvas differ in size, clear whatever the canvas has
+        let canvas = context.canvas().unwrap();
+        context.set_fill_style_str("#FFF");
+        context.fill_rect(0.0, 0.0, canvas.width().into(), canvas.height().into());
     }
 
-    pub fn draw_game_over(&self) {
-        let context: &web_sys::CanvasRenderingContext2d = self.context.as_ref().unwrap();
+    fn draw_text(&self, text: &str, colour: &str) {
+        let context = &self.context;
         context.set_font("bold 30px serif");
         context.set_text_align("center");
-        context.set_fill_style(&JsValue::from_str("#F00"));
-        let result = context.fill_text(
-            "GAME OVER",
-            (self.width / 2).into(),
-            (self.height / 2).into(),
-        );
+        context.set_fill_style_str(colour);
+        let result = context.fill_text(text, (self.width / 2).into(), (self.height / 2).into());
         crate::utils::handle_js_error(result);
     }
 
-    pub fn draw(&self, draw_state: DrawState) {
-        let context = self.context.as_ref().unwrap();
-        let context_next = self.context_next.as_ref().unwrap();
-        self.draw_clear(context);
-        self.draw_clear(context_next);
-        self.draw_block(context, &DrawMode::GAME, draw_state.current_block);
-        self.draw_block(context_next, &DrawMode::PREVIEW, draw_state.next_block);
-        for (_index, row) in draw_state.block_stack.get_stack() {
-            for block in row {
-                self.draw_block(context, &DrawMode::GAME, block);
-            }
-        }
-    }
-
-    fn draw_clear(&self, context: &web_sys::CanvasRenderingContext2d) {
-        context.set_fill_style(&JsValue::from_str("#FFF"));
-        context.fill_rect(0.0, 0.0, self.width.into(), self.height.into());
+    // smaller text in the upper part of the board, leaving the middle free for pause and game over
+    fn draw_callout(&self, text: &str) {
+        let context = &self.context;
+        context.set_font("bold 20px serif");
+        context.set_text_align("center");
+        context.set_fill_style_str("#808");
+        let result = context.fill_text(text, (self.width / 2).into(), (self.height / 4).into());
+        crate::utils::handle_js_error(result);
     }
 
     fn draw_block(
@@ -113,9 +120,9 @@ impl Draw {
         colour_1: &str,
         colour_2: &str,
     ) {
-        let coord = self.calc_coord(&point, mode);
+        let coord = self.calc_coord(point, mode);
         if colour_1 == colour_2 {
-            context.set_fill_style(&JsValue::from_str(colour_1));
+            context.set_fill_style_str(colour_1);
             context.fill_rect(
                 coord.x.into(),
                 coord.y.into(),
@@ -124,7 +131,7 @@ impl Draw {
             );
         } else {
             // draw whole block with colour 2 as background
-            context.set_fill_style(&JsValue::from_str(colour_2));
+            context.set_fill_style_str(colour_2);
             context.fill_rect(
                 coord.x.into(),
                 coord.y.into(),
@@ -133,7 +140,7 @@ impl Draw {
             );
 
             // draw with colour 1 as foreground
-            context.set_fill_style(&JsValue::from_str(colour_1));
+            context.set_fill_style_str(colour_1);
             // upper row
             context.fill_rect(
                 coord.x.into(),
@@ -172,3 +179,99 @@ impl Draw {
         }
     }
 }
+
+impl Renderer for Draw {
+    fn start(&mut self, config: &GameConfig) {
+        self.width = config.width * BLOCK_SIZE;
+        self.height = config.height * BLOCK_SIZE;
+        self.hidden_rows = config.hidden_rows;
+        self.spawn_point = config.get_spawn_point();
+        self.callout = None;
+        let canvas = self.context.canvas().unwrap();
+        canvas.set_width(self.width as u32);
+        canvas.set_height(self.height as u32);
+        let slots = [
+            (&self.context_next, config.preview_count),
+            (&self.context_hold, 1),
+        ];
+        for (context, slot_count) in slots {
+            let canvas = context.canvas().unwrap();
+            canvas.set_width((PREVIEW_WIDTH * BLOCK_SIZE) as u32);
+            canvas.set_height(((slot_count * PREVIEW_SLOT_HEIGHT + 1) * BLOCK_SIZE) as u32);
+        }
+    }
+
+    fn draw_frame(&mut self, draw_state: &DrawState) {
+        let context = &self.context;
+        self.draw_clear(context);
+        if let Some(ghost_block) = draw_state.ghost_block {
+            context.set_global_alpha(GHOST_ALPHA);
+            self.draw_block(context, &DrawMode::Game, ghost_block);
+            context.set_global_alpha(1.0);
+        }
+        self.draw_block(context, &DrawMode::Game, draw_state.current_block);
+        for (point, cell) in draw_state.block_stack.get_cells() {
+            self.draw_piece(
+                context,
+                &DrawMode::Game,
+                &point,
+                cell.colour_1,
+                cell.colour_2,
+            );
+        }
+        // cleared rows fade out until they are removed
+        context.set_global_alpha(draw_state.clear_progress);
+        context.set_fill_style_str("#FFF");
+        for row in draw_state.clearing_rows {
+            let coord = self.calc_coord(&Point { x: 0, y: *row }, &DrawMode::Game);
+            context.fill_rect(
+                coord.x.into(),
+                coord.y.into(),
+                self.width.into(),
+                BLOCK_SIZE.into(),
+            );
+        }
+        context.set_global_alpha(1.0);
+        if let Some((text, frames_left)) = self.callout.take() {
+            self.draw_callout(&text);
+            if frames_left > 1 {
+                self.callout = Some((text, frames_left - 1));
+            }
+        }
+    }
+
+    fn draw_preview(&mut self, next_blocks: &[Block]) {
+        let context_next = &self.context_next;
+        self.draw_clear(context_next);
+        for (slot, next_block) in next_blocks.iter().enumerate() {
+            let mode = DrawMode::Preview { slot: slot as i32 };
+            self.draw_block(context_next, &mode, next_block);
+        }
+    }
+
+    fn draw_hold(&mut self, hold_block: Option<&Block>) {
+        let context_hold = &self.context_hold;
+        self.draw_clear(context_hold);
+        if let Some(hold_block) = hold_block {
+            self.draw_block(context_hold, &DrawMode::Hold, hold_block);
+        }
+    }
+
+    fn draw_t_spin(&mut self, t_spin: TSpin, rows: usize) {
+        let rows = match rows {
+            0 => "",
+            1 => " single",
+            2 => " double",
+            _ => " triple",
+        };
+        self.callout = Some((t_spin.get_name().to_string() + rows, CALLOUT_FRAMES));
+    }
+
+    fn draw_pause(&mut self) {
+        self.draw_text("PAUSE", "#00F");
+    }
+
+    fn draw_game_over(&mut self) {
+        self.draw_text("GAME OVER", "#F00");
+    }
+}
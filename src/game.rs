@@ -1,44 +1,228 @@
+use crate::auto_shift::AutoShift;
 use crate::block_stack;
 use crate::block_stack::BlockStack;
 use crate::blocks;
-use crate::colours;
-use crate::draw::Draw;
-use crate::highscore;
+use crate::gravity;
+use crate::input::InputAction;
+use crate::input::InputEvent;
+use crate::input::InputState;
+use crate::lock_delay::LockDelay;
+use crate::lock_delay::LockReset;
 use crate::objects;
 use crate::objects::DrawState;
-use crate::textdisplay::set_background_colour;
-use crate::textdisplay::update_duration_display;
-use crate::textdisplay::update_text_display;
+use crate::randomizer::Randomizer;
+use crate::randomizer::RandomizerKind;
+use crate::render::Renderer;
+use crate::rotation::Kick;
+use crate::rotation::RotationSystem;
+use crate::scoring::Clear;
+use crate::scoring::ScoringKind;
+use crate::scoring::ScoringRules;
+use crate::snapshot::GameSnapshot;
+use crate::snapshot::SnapshotError;
+use crate::snapshot::SNAPSHOT_VERSION;
+use crate::streaks::Streaks;
+use crate::t_spin;
 use crate::utils::log;
-use crate::utils::random;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
-pub const DEFAULT_INPUT: char = '1';
-pub const GAME_WIDTH: i32 = 16;
-pub const GAME_HEIGHT: i32 = 30;
-const ROWS_FOR_LEVEL_UP: i32 = 10;
-const FRAME_RATE_SPEED_1: i32 = 1000 / 2;
-const SPEED_INCREASE_MS: i32 = 50;
+pub const DEFAULT_BOARD_WIDTH: i32 = 16;
+pub const DEFAULT_BOARD_HEIGHT: i32 = 30;
+pub const MIN_BOARD_WIDTH: i32 = 4;
+pub const MAX_BOARD_WIDTH: i32 = 50;
+pub const MIN_BOARD_HEIGHT: i32 = 4;
+pub const MAX_BOARD_HEIGHT: i32 = 50;
+pub const MAX_HIDDEN_ROWS: i32 = 10;
+pub const DEFAULT_PREVIEW_COUNT: i32 = 3;
+pub const MAX_PREVIEW_COUNT: i32 = 6;
+pub const DEFAULT_LOCK_DELAY_MS: u32 = 500;
+pub const MAX_LOCK_DELAY_MS: u32 = 5000;
+pub const DEFAULT_MAX_LOCK_RESETS: u32 = 15;
+pub const DEFAULT_DAS_MS: u32 = 170;
+pub const MAX_DAS_MS: u32 = 1000;
+pub const DEFAULT_ARR_MS: u32 = 50;
+pub const MAX_ARR_MS: u32 = 500;
+pub const DEFAULT_SOFT_DROP_FACTOR: u32 = 20;
+pub const MAX_SOFT_DROP_FACTOR: u32 = 100;
+pub const MAX_START_LEVEL: i32 = 30;
+pub const MAX_ENTRY_DELAY_MS: u32 = 1000;
+pub const MAX_LINE_CLEAR_DELAY_MS: u32 = 1000;
+// the simulation advances in fixed steps, independent of how often the front-end renders
+pub const TICKS_PER_SECOND: u64 = 60;
+// after e.g. the browser tab was in the background, don't simulate more than this at once
+const MAX_TICKS_PER_FRAME: u64 = TICKS_PER_SECOND;
+
+/// Rules chosen when a game is created.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub randomizer: RandomizerKind,
+    pub rotation: RotationSystem,
+    pub scoring: ScoringKind,
+    // number of columns
+    pub width: i32,
+    // number of rows shown to the player
+    pub height: i32,
+    // rows above the visible ones, blocks spawn there and can be rotated into them
+    pub hidden_rows: i32,
+    // number of upcoming blocks shown
+    pub preview_count: i32,
+    // show where the current block would land
+    pub show_ghost: bool,
+    // ms a block may rest on the stack before it locks
+    pub lock_delay: u32,
+    pub lock_reset: LockReset,
+    // how often moves and rotations may restart the lock delay with `LockReset::Move`
+    pub max_lock_resets: u32,
+    // ms a sideways key has to be held before the block keeps moving
+    pub das: u32,
+    // ms between moves while the key stays held, 0 moves straight to the wall
+    pub arr: u32,
+    // soft drop makes gravity this many times faster, 0 drops to the bottom at once
+    pub soft_drop_factor: u32,
+    // ms between a block locking and the next one appearing
+    pub entry_delay: u32,
+    // ms completed rows stay on the board before they are removed, before the entry delay
+    pub line_clear_delay: u32,
+}
+
+impl GameConfig {
+    pub const fn default() -> Self {
+        Self {
+            randomizer: RandomizerKind::Uniform,
+            rotation: RotationSystem::Srs,
+            scoring: ScoringKind::Legacy,
+            width: DEFAULT_BOARD_WIDTH,
+            height: DEFAULT_BOARD_HEIGHT,
+            hidden_rows: 0,
+            preview_count: DEFAULT_PREVIEW_COUNT,
+            show_ghost: true,
+            lock_delay: DEFAULT_LOCK_DELAY_MS,
+            lock_reset: LockReset::Move,
+            max_lock_resets: DEFAULT_MAX_LOCK_RESETS,
+            das: DEFAULT_DAS_MS,
+            arr: DEFAULT_ARR_MS,
+            soft_drop_factor: DEFAULT_SOFT_DROP_FACTOR,
+            entry_delay: 0,
+            line_clear_delay: 0,
+        }
+    }
+
+    // board dimensions limited to what the game can handle
+    pub(crate) fn validated(mut self) -> Self {
+        self.width = self.width.clamp(MIN_BOARD_WIDTH, MAX_BOARD_WIDTH);
+        self.height = self.height.clamp(MIN_BOARD_HEIGHT, MAX_BOARD_HEIGHT);
+        self.hidden_rows = self.hidden_rows.clamp(0, MAX_HIDDEN_ROWS);
+        self.preview_count = self.preview_count.clamp(1, MAX_PREVIEW_COUNT);
+        self.lock_delay = self.lock_delay.min(MAX_LOCK_DELAY_MS);
+        self.das = self.das.min(MAX_DAS_MS);
+        self.arr = self.arr.min(MAX_ARR_MS);
+        self.soft_drop_factor = self.soft_drop_factor.min(MAX_SOFT_DROP_FACTOR);
+        self.entry_delay = self.entry_delay.min(MAX_ENTRY_DELAY_MS);
+        self.line_clear_delay = self.line_clear_delay.min(MAX_LINE_CLEAR_DELAY_MS);
+        self
+    }
+
+    // rows of the board including the hidden ones
+    pub fn get_total_height(&self) -> i32 {
+        self.height + self.hidden_rows
+    }
+
+    // where new blocks appear, two rows above the visible area if there is room for it
+    pub fn get_spawn_point(&self) -> objects::Point {
+        objects::Point {
+            // the long block reaches two columns to the right
+            x: (self.width / 2).min(self.width - 3),
+            y: (self.hidden_rows - 2).max(0),
+        }
+    }
+}
+
+/// Outcome of a finished game, as needed for the highscore list.
+pub struct GameResult {
+    pub name: String,
+    pub level: i32,
+    pub lines: i32,
+    pub score: i32,
+    pub duration: u32,
+    pub seed: u64,
+    pub scoring: ScoringKind,
+    pub max_combo: u32,
+    pub back_to_backs: u32,
+    pub perfect_clears: u32,
+}
+
+/// Things that happened inside the game the front-end has to present.
+/// Collected while the game runs and handed out via `Game::take_events`.
+pub enum GameEvent {
+    Started { seed: u64 },
+    StatsChanged { score: i32, lines: i32, level: i32 },
+    LevelUp { level: i32 },
+    DurationChanged { duration: u32 },
+    GameOver(GameResult),
+}
+
+// What the game is doing between two blocks. Times are game time in ms.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Phase {
+    // a block is in play
+    Falling,
+    // completed rows are shown until they are removed
+    LineClear {
+        start: u64,
+        end: u64,
+        rows: Vec<i32>,
+    },
+    // waiting for the next block to appear
+    Entry {
+        end: u64,
+    },
+}
 
 pub struct Game {
     score: i32,
     lines: i32,
     level: i32,
+    // level progression of some scoring rules depends on it
+    start_level: i32,
     name: String,
-    draw: Option<Draw>,
+    config: GameConfig,
+    randomizer: Randomizer,
+    events: Vec<GameEvent>,
+    renderer: Option<Box<dyn Renderer>>,
+    phase: Phase,
+    // no pieces while the phase is not `Phase::Falling`
     current_block: blocks::Block,
-    next_block: blocks::Block,
+    // upcoming blocks, the first one is next
+    next_blocks: Vec<blocks::Block>,
+    // block kept aside by the player
+    hold_block: Option<blocks::Block>,
+    // hold can be used only once until the current block locks
+    hold_used: bool,
+    // kick used by the last rotation, None if the block moved since, see `t_spin::detect`
+    last_kick: Option<Kick>,
+    // rotation and hold pressed during the delays, applied to the next block when it appears
+    buffered_turns: i32,
+    buffered_hold: bool,
+    streaks: Streaks,
+    lock_delay: LockDelay,
     block_stack: Option<block_stack::BlockStack>,
-    movement: objects::Movement,
+    // movements from input, applied with the next world update
+    movements: Vec<objects::Movement>,
     over: bool,
     pause: bool,
-    input: char,
-    input_processed: bool,
-    pressed: bool,
-    speed_increase: bool,
-    timestamp_last_frame: u32,
+    input_queue: VecDeque<InputEvent>,
+    auto_shift: AutoShift,
+    soft_drop: bool,
+    // simulation steps done so far
+    tick_count: u64,
+    // how far the current block has fallen towards the next row, see `gravity::SUBCELLS_PER_CELL`
+    fall_progress: u32,
     timestamp_game_start: u32,
     timestamp_pause_start: u32,
     pause_duration_sum: u32,
+    // game time played before the game was restored from a snapshot
+    duration_offset: u32,
 }
 
 impl Game {
@@ -47,28 +231,48 @@ impl Game {
             score: 0,
             lines: 0,
             level: 1,
+            start_level: 1,
             name: String::new(),
-            draw: None,
+            config: GameConfig::default(),
+            randomizer: Randomizer::new(RandomizerKind::Uniform, 0),
+            events: Vec::new(),
+            renderer: None,
+            phase: Phase::Falling,
             current_block: blocks::default_block(),
-            next_block: blocks::default_block(),
+            next_blocks: Vec::new(),
+            hold_block: None,
+            hold_used: false,
+            last_kick: None,
+            buffered_turns: 0,
+            buffered_hold: false,
+            streaks: Streaks::default(),
+            lock_delay: LockDelay::new(DEFAULT_LOCK_DELAY_MS, LockReset::Move, 0),
             block_stack: None,
-            movement: objects::Movement::NONE,
+            movements: Vec::new(),
             over: true,
             pause: false,
-            input: DEFAULT_INPUT,
-            input_processed: false,
-            pressed: false,
-            speed_increase: false,
-            timestamp_last_frame: 0,
+            input_queue: VecDeque::new(),
+            auto_shift: AutoShift::new(DEFAULT_DAS_MS, DEFAULT_ARR_MS),
+            soft_drop: false,
+            tick_count: 0,
+            fall_progress: 0,
             timestamp_game_start: 0,
             timestamp_pause_start: 0,
             pause_duration_sum: 0,
+            duration_offset: 0,
         }
     }
-    pub fn set_state(&mut self, name: &str, start_level: i32, draw: Draw) {
+    pub fn set_state(
+        &mut self,
+        name: &str,
+        start_level: i32,
+        seed: u64,
+        config: GameConfig,
+        renderer: Box<dyn Renderer>,
+    ) {
         log!("  re-setting game state!");
 
-        let level_to_use = if start_level < 0 || start_level > 9 {
+        let level_to_use = if !(0..=MAX_START_LEVEL).contains(&start_level) {
             1
         } else {
             start_level
@@ -77,147 +281,426 @@ impl Game {
         self.score = 0;
         self.lines = 0;
         self.level = level_to_use;
+        self.start_level = level_to_use;
         self.name = name.to_string();
-        self.draw = Some(draw);
+        self.config = config.validated();
+        self.randomizer = Randomizer::new(self.config.randomizer, seed);
+        self.events.clear();
+        self.renderer = Some(renderer);
+        self.phase = Phase::Falling;
         self.current_block = self.create_block();
-        self.next_block = self.create_block();
-        self.block_stack = Some(BlockStack::new());
-        self.movement = objects::Movement::NONE;
+        self.next_blocks = (0..self.config.preview_count)
+            .map(|_| self.create_block())
+            .collect();
+        self.hold_block = None;
+        self.hold_used = false;
+        self.last_kick = None;
+        self.buffered_turns = 0;
+        self.buffered_hold = false;
+        self.streaks = Streaks::default();
+        self.lock_delay = LockDelay::new(
+            self.config.lock_delay,
+            self.config.lock_reset,
+            self.config.max_lock_resets,
+        );
+        self.block_stack = Some(BlockStack::new(
+            self.config.width,
+            self.config.get_total_height(),
+        ));
+        self.movements.clear();
         self.over = false;
         self.pause = false;
-        self.input = DEFAULT_INPUT;
-        self.input_processed = true;
-        self.pressed = false;
-        self.speed_increase = false;
-        self.timestamp_last_frame = 0;
+        self.input_queue.clear();
+        self.auto_shift = AutoShift::new(self.config.das, self.config.arr);
+        self.soft_drop = false;
+        self.tick_count = 0;
+        self.fall_progress = 0;
         self.timestamp_game_start = 0;
         self.timestamp_pause_start = 0;
         self.pause_duration_sum = 0;
-        set_background_colour("#FFF");
-        update_text_display(self.score, self.lines, self.level);
+        self.duration_offset = 0;
+        self.renderer.as_mut().unwrap().start(&self.config);
+        self.events.push(GameEvent::Started { seed });
+        self.push_stats_changed();
+    }
+
+    /// Everything needed to continue the running game later, see `restore`.
+    pub fn save(&self) -> GameSnapshot {
+        GameSnapshot {
+            version: SNAPSHOT_VERSION,
+            name: self.name.clone(),
+            score: self.score,
+            lines: self.lines,
+            level: self.level,
+            start_level: self.start_level,
+            config: self.config.clone(),
+            randomizer: self.randomizer.clone(),
+            phase: self.phase.clone(),
+            current_block: self.current_block.clone(),
+            next_blocks: self.next_blocks.clone(),
+            hold_block: self.hold_block.clone(),
+            hold_used: self.hold_used,
+            last_kick: self.last_kick,
+            buffered_turns: self.buffered_turns,
+            buffered_hold: self.buffered_hold,
+            streaks: self.streaks.clone(),
+            lock_delay: self.lock_delay.clone(),
+            block_stack: self.block_stack.clone().unwrap(),
+            pause: self.pause,
+            tick_count: self.tick_count,
+            fall_progress: self.fall_progress,
+        }
+    }
+
+    /// Continues a game saved with `save`. Time starts running again with the next world loop.
+    pub fn restore(
+        &mut self,
+        snapshot: GameSnapshot,
+        renderer: Box<dyn Renderer>,
+    ) -> Result<(), SnapshotError> {
+        snapshot.check()?;
+        log!("  restoring game state!");
+
+        self.score = snapshot.score;
+        self.lines = snapshot.lines;
+        self.level = snapshot.level;
+        self.start_level = snapshot.start_level;
+        self.name = snapshot.name;
+        self.config = snapshot.config;
+        self.randomizer = snapshot.randomizer;
+        self.events.clear();
+        self.renderer = Some(renderer);
+        self.phase = snapshot.phase;
+        self.current_block = snapshot.current_block;
+        self.next_blocks = snapshot.next_blocks;
+        self.hold_block = snapshot.hold_block;
+        self.hold_used = snapshot.hold_used;
+        self.last_kick = snapshot.last_kick;
+        self.buffered_turns = snapshot.buffered_turns;
+        self.buffered_hold = snapshot.buffered_hold;
+        self.streaks = snapshot.streaks;
+        self.lock_delay = snapshot.lock_delay;
+        self.block_stack = Some(snapshot.block_stack);
+        self.movements.clear();
+        self.over = false;
+        self.pause = snapshot.pause;
+        self.input_queue.clear();
+        self.auto_shift = AutoShift::new(self.config.das, self.config.arr);
+        self.soft_drop = false;
+        self.tick_count = snapshot.tick_count;
+        self.fall_progress = snapshot.fall_progress;
+        self.timestamp_game_start = 0;
+        self.timestamp_pause_start = 0;
+        self.pause_duration_sum = 0;
+        self.duration_offset = (self.tick_count * 1000 / TICKS_PER_SECOND) as u32;
+        self.renderer.as_mut().unwrap().start(&self.config);
+        self.render();
+        self.events.push(GameEvent::Started {
+            seed: self.randomizer.get_seed(),
+        });
+        self.push_stats_changed();
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> &GameConfig {
+        &self.config
     }
 
     pub fn is_over(&self) -> bool {
         self.over
     }
 
-    fn create_block(&self) -> blocks::Block {
-        let rand = random(0, 6);
-        blocks::new(rand, self.level)
+    /// Hands out all events collected since the last call.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
     }
 
+    fn render(&mut self) {
+        let ghost_block = if self.config.show_ghost && self.phase == Phase::Falling {
+            Some(self.current_block.get_moved(0, self.get_drop_distance()))
+        } else {
+            None
+        };
+        let (clearing_rows, clear_progress) = match &self.phase {
+            Phase::LineClear { start, end, rows } => {
+                let elapsed = self.get_game_time().min(*end) - start;
+                (rows.as_slice(), elapsed as f64 / (end - start) as f64)
+            }
+            _ => (&[][..], 0.0),
+        };
+        let renderer = self.renderer.as_mut().unwrap();
+        renderer.draw_frame(&DrawState {
+            current_block: &self.current_block,
+            ghost_block: ghost_block.as_ref(),
+            block_stack: self.block_stack.as_ref().unwrap(),
+            clearing_rows,
+            clear_progress,
+        });
+        renderer.draw_preview(&self.next_blocks);
+        renderer.draw_hold(self.hold_block.as_ref());
+    }
+
+    fn push_stats_changed(&mut self) {
+        self.events.push(GameEvent::StatsChanged {
+            score: self.score,
+            lines: self.lines,
+            level: self.level,
+        });
+    }
+
+    fn create_block(&mut self) -> blocks::Block {
+        let rand = self.randomizer.next_block_index();
+        blocks::new(rand, self.level, &self.config.get_spawn_point())
+    }
+
+    // first block of the queue, which is refilled from the randomizer
+    fn take_next_block(&mut self) -> blocks::Block {
+        let next_block = self.next_blocks.remove(0);
+        let new_block = self.create_block();
+        self.next_blocks.push(new_block);
+        next_block
+    }
+
+    // Called once per rendered frame. Runs all simulation steps due up to `timestamp`, then renders.
     pub fn world_loop_contents(&mut self, timestamp: u32) -> bool {
-        let mut first_frame = false;
+        if self.over {
+            return true;
+        }
         if self.timestamp_game_start == 0 {
             self.timestamp_game_start = timestamp;
-            first_frame = true;
-        }
-        if !self.over && self.enough_time_passed(timestamp) {
-            self.process_input(timestamp);
             if self.pause {
-                self.draw.as_ref().unwrap().draw_pause();
-            } else {
-                let continue_rendering = self.update_world(first_frame);
-                if continue_rendering {
-                    update_duration_display(self.calc_duration(timestamp));
-                    self.draw.as_ref().unwrap().draw(DrawState {
-                        current_block: &self.current_block,
-                        next_block: &self.next_block,
-                        block_stack: &self.block_stack.as_ref().unwrap(),
-                    });
-                } else {
+                // restored while paused
+                self.timestamp_pause_start = timestamp;
+            }
+        }
+        if self.pause {
+            // only input can end the pause, time does not advance
+            self.process_input();
+        }
+        if !self.pause {
+            let target_tick = self.calc_duration(timestamp) as u64 * TICKS_PER_SECOND / 1000;
+            if target_tick > self.tick_count + MAX_TICKS_PER_FRAME {
+                self.tick_count = target_tick - MAX_TICKS_PER_FRAME;
+            }
+            while self.tick_count < target_tick && !self.pause {
+                self.tick_count += 1;
+                if !self.tick() {
                     self.game_over(timestamp);
                     return false;
                 }
             }
         }
+        if self.pause {
+            self.renderer.as_mut().unwrap().draw_pause();
+        } else {
+            self.events.push(GameEvent::DurationChanged {
+                duration: self.calc_duration(timestamp),
+            });
+            self.render();
+        }
         true
     }
 
-    fn enough_time_passed(&mut self, timestamp: u32) -> bool {
-        if timestamp > self.timestamp_last_frame {
-            let mut threshold = self.frame_time_threshold();
-            if threshold < 0 {
-                threshold = 0;
+    // One simulation step: input first, then gravity. Returns false if the game is over.
+    fn tick(&mut self) -> bool {
+        self.process_input();
+        if self.pause {
+            return true;
+        }
+        let time = self.get_game_time();
+        let fits = match self.phase {
+            Phase::LineClear { end, .. } if time >= end => {
+                self.block_stack.as_mut().unwrap().reduce_stack();
+                self.start_entry(time)
             }
-            //log!("frame_time_threshold: {}", threshold);
-            if (timestamp - self.timestamp_last_frame) as i32 > threshold {
-                self.timestamp_last_frame = timestamp;
-                return true;
+            Phase::Entry { end } if time >= end => self.spawn_block(),
+            _ => true,
+        };
+        if !fits {
+            return false;
+        }
+        for movement in std::mem::take(&mut self.movements) {
+            if !self.apply_movement(movement) {
+                return false;
             }
         }
-        false
+        if self.phase != Phase::Falling {
+            // sideways keys keep charging, but repeats due without a block in play are dropped
+            self.auto_shift.update(time);
+            return true;
+        }
+        let direction = self.auto_shift.get_direction();
+        for _ in 0..self.auto_shift.update(time).min(self.config.width as u32) {
+            if !self.try_move(direction, 0) {
+                break;
+            }
+        }
+        if self.soft_drop && self.config.soft_drop_factor == 0 {
+            self.fall_progress = 0;
+            while self.move_block_down() {
+                self.add_soft_drop_points();
+            }
+        } else {
+            self.fall_progress += self.get_gravity();
+            while self.fall_progress >= gravity::SUBCELLS_PER_CELL {
+                self.fall_progress -= gravity::SUBCELLS_PER_CELL;
+                if !self.move_block_down() {
+                    // landed, fractions don't carry over to the time on the ground
+                    self.fall_progress = 0;
+                    break;
+                }
+                if self.soft_drop {
+                    self.add_soft_drop_points();
+                }
+            }
+        }
+        let grounded = self.get_drop_distance() == 0;
+        if self.lock_delay.update(time, grounded) {
+            return self.lock_block(0);
+        }
+        true
     }
 
-    fn frame_time_threshold(&self) -> i32 {
-        if self.speed_increase {
-            FRAME_RATE_SPEED_1 - 8 * SPEED_INCREASE_MS
+    // ms of simulated time, without pauses
+    fn get_game_time(&self) -> u64 {
+        self.tick_count * 1000 / TICKS_PER_SECOND
+    }
+
+    // cells per frame in fractions of a cell, soft drop multiplies it by its factor
+    fn get_gravity(&self) -> u32 {
+        let gravity = self.get_rules().get_gravity_curve().get_gravity(self.level);
+        if self.soft_drop {
+            gravity * self.config.soft_drop_factor.max(1)
         } else {
-            FRAME_RATE_SPEED_1 - self.level * SPEED_INCREASE_MS
+            gravity
         }
     }
 
+    fn get_rules(&self) -> &'static dyn ScoringRules {
+        self.config.scoring.get_rules()
+    }
+
+    fn add_soft_drop_points(&mut self) {
+        self.score += self.get_rules().get_soft_drop_points_per_row();
+        self.push_stats_changed();
+    }
+
     fn calc_duration(&self, timestamp: u32) -> u32 {
-        timestamp - self.timestamp_game_start - self.pause_duration_sum
+        timestamp - self.timestamp_game_start - self.pause_duration_sum + self.duration_offset
+    }
+
+    // game time of an input event, events from before the game (re)started count as its start
+    fn get_event_time(&self, timestamp: u32) -> u64 {
+        let since_start =
+            timestamp.saturating_sub(self.timestamp_game_start + self.pause_duration_sum);
+        (since_start + self.duration_offset) as u64
     }
 
     fn game_over(&mut self, timestamp: u32) {
         log!("game over");
         self.over = true;
-        self.draw.as_ref().unwrap().draw_game_over();
-        let duration = self.calc_duration(timestamp);
-        let latest_timestamp =
-            highscore::add_score(&self.name, self.level, self.lines, self.score, duration);
-        highscore::print_highscores(latest_timestamp);
+        self.renderer.as_mut().unwrap().draw_game_over();
+        self.events.push(GameEvent::GameOver(GameResult {
+            name: self.name.clone(),
+            level: self.level,
+            lines: self.lines,
+            score: self.score,
+            duration: self.calc_duration(timestamp),
+            seed: self.randomizer.get_seed(),
+            scoring: self.config.scoring,
+            max_combo: self.streaks.get_max_combo(),
+            back_to_backs: self.streaks.get_back_to_backs(),
+            perfect_clears: self.streaks.get_perfect_clears(),
+        }));
     }
 
     fn toggle_pause(&mut self, timestamp: u32) {
         log!("toggling pause");
         if !self.pause {
-            self.timestamp_pause_start = timestamp;
+            // input from before the game (re)started pauses it at its start, as in get_event_time
+            self.timestamp_pause_start =
+                timestamp.max(self.timestamp_game_start + self.pause_duration_sum);
         } else {
-            self.pause_duration_sum += timestamp - self.timestamp_pause_start;
+            self.pause_duration_sum += timestamp.saturating_sub(self.timestamp_pause_start);
             self.timestamp_pause_start = 0;
         }
         self.pause = !self.pause;
-        self.input = DEFAULT_INPUT;
     }
 
-    pub fn set_input(&mut self, input: char) {
-        self.input = input;
-        self.input_processed = false;
-        self.speed_increase = false;
+    pub fn push_input(&mut self, event: InputEvent) {
+        self.input_queue.push_back(event);
     }
 
-    pub fn set_pressed(&mut self, pressed: bool) {
-        self.pressed = pressed;
-        if pressed {
-            if self.input == 's' {
-                self.speed_increase = true;
-            }
-        } else {
-            self.speed_increase = false;
-        }
+    pub fn press(&mut self, action: InputAction, timestamp: u32) {
+        self.push_input(InputEvent {
+            action,
+            state: InputState::Pressed,
+            timestamp,
+        });
     }
 
-    fn process_input(&mut self, timestamp: u32) {
-        match &self.input {
-            'a' => self.movement = objects::Movement::LEFT,
-            'd' => self.movement = objects::Movement::RIGHT,
-            'q' => self.movement = objects::Movement::RotateLeft,
-            'e' => self.movement = objects::Movement::RotateRight,
-            'p' => {
-                if !self.input_processed {
-                    self.print_debug_info();
-                    self.input_processed = true;
-                }
+    pub fn release(&mut self, action: InputAction, timestamp: u32) {
+        self.push_input(InputEvent {
+            action,
+            state: InputState::Released,
+            timestamp,
+        });
+    }
+
+    // Consumes the input that happened up to the current tick, in order. Every press counts even if
+    // several arrived since the last frame. While paused all queued input is consumed.
+    fn process_input(&mut self) {
+        while let Some(event) = self.input_queue.front().copied() {
+            let time = self.get_event_time(event.timestamp);
+            if !self.pause && time > self.get_game_time() {
+                // after this tick, stays queued for a later one
+                break;
             }
-            ' ' => {
-                self.toggle_pause(timestamp);
-                self.input_processed = true;
-                self.movement = objects::Movement::NONE;
+            self.input_queue.pop_front();
+            // releases still count, so no key stays held after the pause
+            if self.pause
+                && event.action != InputAction::Pause
+                && event.state == InputState::Pressed
+            {
+                continue;
+            }
+            match (event.action, event.state) {
+                (InputAction::MoveLeft, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::Left);
+                    self.auto_shift.press(-1, time);
+                }
+                (InputAction::MoveLeft, InputState::Released) => self.auto_shift.release(-1, time),
+                (InputAction::MoveRight, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::Right);
+                    self.auto_shift.press(1, time);
+                }
+                (InputAction::MoveRight, InputState::Released) => self.auto_shift.release(1, time),
+                (InputAction::RotateLeft, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::RotateLeft)
+                }
+                (InputAction::RotateRight, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::RotateRight)
+                }
+                (InputAction::Rotate180, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::Rotate180)
+                }
+                (InputAction::HardDrop, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::HardDrop)
+                }
+                (InputAction::SonicDrop, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::SonicDrop)
+                }
+                (InputAction::Hold, InputState::Pressed) => {
+                    self.movements.push(objects::Movement::Hold)
+                }
+                (InputAction::SoftDrop, state) => self.soft_drop = state == InputState::Pressed,
+                (InputAction::Pause, InputState::Pressed) => {
+                    self.toggle_pause(event.timestamp);
+                    self.movements.clear();
+                }
+                (InputAction::DebugInfo, InputState::Pressed) => self.print_debug_info(),
+                (_, InputState::Released) => (),
             }
-            _ => self.movement = objects::Movement::NONE,
         }
     }
 
@@ -228,153 +711,942 @@ impl Game {
             log!("  x: {}, y: {}", piece.x, piece.y);
         }
         log!("block stack:");
-        for (_index, row) in self.block_stack.as_ref().unwrap().get_stack() {
-            for stack_block in row {
-                for piece in stack_block.get_pieces() {
-                    log!("  x: {}, y: {}", piece.x, piece.y);
-                }
-            }
+        for (piece, _cell) in self.block_stack.as_ref().unwrap().get_cells() {
+            log!("  x: {}, y: {}", piece.x, piece.y);
         }
         log!("******************************************************************************");
     }
 
-    fn update_world(&mut self, first_frame: bool) -> bool {
-        let block_ok = self.move_block_and_check_collision(first_frame);
-        if !block_ok {
-            let mut block_still_on_first_row = false;
-            for point in self.current_block.get_pieces() {
-                if point.y == 0 {
-                    block_still_on_first_row = true;
-                }
-            }
-            if block_still_on_first_row {
-                return false;
-            } else {
-                let block_stack = self.block_stack.as_mut().unwrap();
-                block_stack.add_block_to_stack(&self.current_block);
-                let num_of_rows = block_stack.reduce_stack(GAME_WIDTH);
-                if num_of_rows > 0 {
-                    self.handle_rows_removed(num_of_rows);
-                }
-                self.current_block = self.next_block.clone();
-                self.next_block = self.create_block();
+    // Puts the current block onto the stack and brings in the next one.
+    // Returns false if that ends the game.
+    fn lock_block(&mut self, hard_drop_rows: i32) -> bool {
+        // game over if the block lands where it spawned or above the visible area
+        let first_free_row = self.config.hidden_rows.max(1);
+        let mut block_still_on_first_row = false;
+        for point in self.current_block.get_pieces() {
+            if point.y < first_free_row {
+                block_still_on_first_row = true;
             }
         }
-        true
+        if block_still_on_first_row {
+            return false;
+        }
+        let block_stack = self.block_stack.as_mut().unwrap();
+        let t_spin = t_spin::detect(&self.current_block, block_stack, self.last_kick);
+        block_stack.add_block_to_stack(&self.current_block);
+        let complete_rows = block_stack.get_complete_rows();
+        let num_of_rows = complete_rows.len();
+        // nothing left but the complete rows
+        let empty = block_stack.get_cells().count() == num_of_rows * self.config.width as usize;
+        let difficult = num_of_rows == 4 || (t_spin.is_some() && num_of_rows > 0);
+        let bonus = self.streaks.lock(num_of_rows, difficult, empty);
+        if let Some(t_spin) = t_spin {
+            self.renderer
+                .as_mut()
+                .unwrap()
+                .draw_t_spin(t_spin, num_of_rows);
+        }
+        if num_of_rows > 0 || hard_drop_rows > 0 || t_spin.is_some() {
+            let clear = Clear {
+                rows: num_of_rows,
+                t_spin,
+                bonus,
+            };
+            self.handle_rows_removed(&clear, hard_drop_rows);
+        }
+        self.current_block = blocks::default_block();
+        let time = self.get_game_time();
+        if num_of_rows > 0 && self.config.line_clear_delay > 0 {
+            self.phase = Phase::LineClear {
+                start: time,
+                end: time + self.config.line_clear_delay as u64,
+                rows: complete_rows,
+            };
+            true
+        } else {
+            self.block_stack.as_mut().unwrap().reduce_stack();
+            self.start_entry(time)
+        }
     }
 
-    fn handle_rows_removed(&mut self, num_of_rows: usize) {
-        self.lines = self.lines + num_of_rows as i32;
-        let lines_threshold = self.level * ROWS_FOR_LEVEL_UP;
-        if self.lines > lines_threshold - 1 {
-            self.level_up();
+    // After a block locked and its rows are gone. Returns false if the next block does not fit.
+    fn start_entry(&mut self, time: u64) -> bool {
+        if self.config.entry_delay > 0 {
+            self.phase = Phase::Entry {
+                end: time + self.config.entry_delay as u64,
+            };
+            true
+        } else {
+            self.spawn_block()
         }
-        match num_of_rows {
-            1 => self.score = self.score + 1 * self.level,
-            2 => self.score = self.score + 3 * self.level,
-            3 => self.score = self.score + 4 * self.level,
-            4 => self.score = self.score + 8 * self.level,
-            _ => (),
+    }
+
+    // Brings in the next block, with the hold and rotation buffered during the delays.
+    // Returns false if there is no room left for it.
+    fn spawn_block(&mut self) -> bool {
+        self.phase = Phase::Falling;
+        self.current_block = self.take_next_block();
+        self.hold_used = false;
+        self.last_kick = None;
+        self.lock_delay.start_block();
+        // the next block gets a full gravity step before it falls
+        self.fall_progress = 0;
+        let mut fits = self.block_stack.as_ref().unwrap().fits(&self.current_block);
+        if std::mem::take(&mut self.buffered_hold) && fits {
+            fits = self.hold();
+        }
+        // one of -1, 1 and 2
+        let turns = (std::mem::take(&mut self.buffered_turns) + 1).rem_euclid(4) - 1;
+        if turns != 0 && fits {
+            self.try_rotate(turns);
         }
-        update_text_display(self.score, self.lines, self.level);
+        fits
+    }
+
+    // scoring for a locked block, for what it cleared and the distance it was hard dropped
+    fn handle_rows_removed(&mut self, clear: &Clear, hard_drop_rows: i32) {
+        let rules = self.get_rules();
+        self.lines += clear.rows as i32;
+        // locks without a clear only score, e.g. level 0 with 0 lines would count as a level up
+        if clear.rows > 0 && rules.is_level_up(self.start_level, self.level, self.lines) {
+            self.level_up();
+        }
+        self.score += rules.get_clear_points(clear, self.level);
+        self.score += hard_drop_rows * rules.get_hard_drop_points_per_row();
+        self.push_stats_changed();
     }
 
     fn level_up(&mut self) {
-        self.level = self.level + 1;
-        set_background_colour(colours::colours_for_level(self.level).colour_bg);
-        // re-create next_block with new color
-        let rand = blocks::rand_for_block(&self.next_block);
-        self.next_block = blocks::new(rand, self.level);
+        self.level += 1;
+        self.events.push(GameEvent::LevelUp { level: self.level });
+        // re-create next_blocks with new color
+        let spawn = self.config.get_spawn_point();
+        for next_block in self.next_blocks.iter_mut() {
+            let rand = blocks::rand_for_block(next_block);
+            *next_block = blocks::new(rand, self.level, &spawn);
+        }
     }
 
-    fn move_block_and_check_collision(&mut self, first_frame: bool) -> bool {
-        let block_stack = self.block_stack.as_ref().unwrap();
-        if !first_frame {
-            let mut all_pieces_ok = true;
-            for point in self.current_block.get_pieces_mut() {
-                if point.y >= GAME_HEIGHT - 1 {
-                    all_pieces_ok = false;
-                }
-                if all_pieces_ok {
-                    all_pieces_ok = block_stack.check_collision(&objects::Point {
-                        x: point.x,
-                        y: point.y + 1,
-                    });
-                }
+    fn move_block_down(&mut self) -> bool {
+        self.try_move(0, 1)
+    }
+
+    // number of rows the current block can fall before it lands
+    pub fn get_drop_distance(&self) -> i32 {
+        self.block_stack
+            .as_ref()
+            .unwrap()
+            .get_drop_distance(&self.current_block)
+    }
+
+    // Returns false if the drop ended the game.
+    fn hard_drop(&mut self) -> bool {
+        let distance = self.get_drop_distance();
+        if distance > 0 {
+            self.current_block = self.current_block.get_moved(0, distance);
+            self.last_kick = None;
+        }
+        self.lock_block(distance)
+    }
+
+    // Swaps the current block with the held one, or the next one if nothing is held yet.
+    // Returns false if the block brought in does not fit.
+    fn hold(&mut self) -> bool {
+        if self.hold_used {
+            return true;
+        }
+        let spawn = self.config.get_spawn_point();
+        // held blocks start over at the spawn point in spawn orientation
+        let held = blocks::new(
+            blocks::rand_for_block(&self.current_block),
+            self.level,
+            &spawn,
+        );
+        self.current_block = match self.hold_block.take() {
+            Some(hold_block) => {
+                blocks::new(blocks::rand_for_block(&hold_block), self.level, &spawn)
             }
-            if all_pieces_ok {
-                for point in self.current_block.get_pieces_mut() {
-                    point.y += 1;
-                }
+            None => self.take_next_block(),
+        };
+        self.hold_block = Some(held);
+        self.hold_used = true;
+        self.last_kick = None;
+        self.lock_delay.start_block();
+        self.fall_progress = 0;
+        self.block_stack.as_ref().unwrap().fits(&self.current_block)
+    }
+
+    fn sonic_drop(&mut self) {
+        while self.move_block_down() {}
+    }
+
+    // moves the current block if it fits at the new position
+    fn try_move(&mut self, dx: i32, dy: i32) -> bool {
+        let moved = self.current_block.get_moved(dx, dy);
+        let fits = self.block_stack.as_ref().unwrap().fits(&moved);
+        if fits {
+            self.current_block = moved;
+            self.last_kick = None;
+            if dy > 0 {
+                self.lock_delay.fell_to(self.current_block.get_lowest_row());
             } else {
-                return false;
+                self.lock_delay.moved(self.get_game_time());
             }
         }
+        fits
+    }
 
-        if self.input_processed {
-            return true;
+    // turns the current block, using the first kick of the rotation system where it fits
+    fn try_rotate(&mut self, turns: i32) -> bool {
+        let block_stack = self.block_stack.as_ref().unwrap();
+        let rotated = self.current_block.get_rotated(turns);
+        let kicks = self.config.rotation.get_kicks(
+            rotated.get_block_type(),
+            self.current_block.get_rotation(),
+            turns,
+        );
+        let kicked = kicks
+            .into_iter()
+            .map(|(dx, dy)| rotated.get_moved(dx, dy))
+            .enumerate()
+            .find(|(_, kicked)| block_stack.fits(kicked));
+        match kicked {
+            Some((index, kicked)) => {
+                self.current_block = kicked;
+                self.last_kick = Some(Kick { turns, index });
+                self.lock_delay.moved(self.get_game_time());
+                true
+            }
+            None => false,
         }
-        self.input_processed = true;
+    }
 
-        if self.movement == objects::Movement::LEFT {
-            let mut all_pieces_ok = true;
-            for point in self.current_block.get_pieces() {
-                if point.x <= 0 {
-                    all_pieces_ok = false;
-                }
-                if all_pieces_ok {
-                    all_pieces_ok = block_stack.check_collision(&objects::Point {
-                        x: point.x - 1,
-                        y: point.y,
-                    });
-                }
+    // no block in play, remember turns and hold for the next one
+    fn buffer_movement(&mut self, movement: objects::Movement) {
+        match movement {
+            objects::Movement::RotateLeft => self.buffered_turns -= 1,
+            objects::Movement::RotateRight => self.buffered_turns += 1,
+            objects::Movement::Rotate180 => self.buffered_turns += 2,
+            objects::Movement::Hold => self.buffered_hold = true,
+            _ => (),
+        }
+    }
+
+    // Returns false if the movement ended the game.
+    fn apply_movement(&mut self, movement: objects::Movement) -> bool {
+        if self.phase != Phase::Falling {
+            self.buffer_movement(movement);
+            return true;
+        }
+        match movement {
+            objects::Movement::None => (),
+            objects::Movement::Left => {
+                self.try_move(-1, 0);
             }
-            if all_pieces_ok {
-                for point in self.current_block.get_pieces_mut() {
-                    point.x -= 1;
-                }
+            objects::Movement::Right => {
+                self.try_move(1, 0);
+            }
+            objects::Movement::RotateLeft => {
+                self.try_rotate(-1);
             }
+            objects::Movement::RotateRight => {
+                self.try_rotate(1);
+            }
+            objects::Movement::Rotate180 => {
+                self.try_rotate(2);
+            }
+            objects::Movement::HardDrop => return self.hard_drop(),
+            objects::Movement::SonicDrop => self.sonic_drop(),
+            objects::Movement::Hold => return self.hold(),
         }
-        if self.movement == objects::Movement::RIGHT {
-            let mut all_pieces_ok = true;
-            for point in self.current_block.get_pieces() {
-                if point.x > GAME_WIDTH - 2 {
-                    all_pieces_ok = false;
-                }
-                if all_pieces_ok {
-                    all_pieces_ok = block_stack.check_collision(&objects::Point {
-                        x: point.x + 1,
-                        y: point.y,
-                    });
-                }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RecordingRenderer;
+    use crate::render::RenderCall;
+    use crate::t_spin::TSpin;
+
+    const STEP_MS: u32 = 1000;
+
+    fn start_game() -> Game {
+        let mut game = Game::default();
+        game.set_state(
+            "test",
+            1,
+            5,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        game.world_loop_contents(STEP_MS);
+        game
+    }
+
+    fn min_x(game: &Game) -> i32 {
+        game.current_block
+            .get_pieces()
+            .iter()
+            .map(|point| point.x)
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn presses_between_frames_are_not_lost() {
+        let mut game = start_game();
+        let x = min_x(&game);
+        game.press(InputAction::MoveLeft, STEP_MS + 10);
+        game.release(InputAction::MoveLeft, STEP_MS + 20);
+        game.press(InputAction::MoveLeft, STEP_MS + 30);
+        game.release(InputAction::MoveLeft, STEP_MS + 40);
+        game.world_loop_contents(2 * STEP_MS);
+        assert_eq!(min_x(&game), x - 2);
+    }
+
+    #[test]
+    fn input_is_applied_at_its_own_tick_when_frames_are_late() {
+        let hold_left = |game: &mut Game| {
+            game.press(InputAction::MoveLeft, STEP_MS + 10);
+            game.release(InputAction::MoveLeft, STEP_MS + 300);
+        };
+        let mut smooth = start_game();
+        let mut late = start_game();
+        hold_left(&mut smooth);
+        hold_left(&mut late);
+        for timestamp in (STEP_MS..STEP_MS + 400).step_by(17) {
+            smooth.world_loop_contents(timestamp);
+        }
+        smooth.world_loop_contents(STEP_MS + 400);
+        late.world_loop_contents(STEP_MS + 400);
+        // held through the delayed auto shift and some repeats
+        assert!(min_x(&late) < min_x(&start_game()) - 2);
+        assert_eq!(min_x(&late), min_x(&smooth));
+    }
+
+    #[test]
+    fn pause_lasts_from_its_press_to_its_release_not_to_the_frames() {
+        let mut game = start_game();
+        game.press(InputAction::Pause, STEP_MS + 50);
+        game.world_loop_contents(STEP_MS + 100);
+        assert!(game.pause);
+        assert_eq!(game.tick_count, 3);
+        game.press(InputAction::Pause, STEP_MS + 1010);
+        game.world_loop_contents(STEP_MS + 1100);
+        assert!(!game.pause);
+        // paused for 960 ms, so 140 ms of game time have passed
+        assert_eq!(game.tick_count, 8);
+    }
+
+    #[test]
+    fn hard_drop_without_a_clear_keeps_level_0() {
+        let mut game = Game::default();
+        game.set_state(
+            "test",
+            0,
+            5,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        game.world_loop_contents(STEP_MS);
+        assert!(game.apply_movement(objects::Movement::HardDrop));
+        assert!(game.score > 0);
+        assert_eq!((game.level, game.lines), (0, 0));
+    }
+
+    #[test]
+    fn input_is_applied_with_the_next_tick_not_the_next_gravity_step() {
+        let mut game = start_game();
+        let x = min_x(&game);
+        let y = game.current_block.get_pieces()[0].y;
+        game.press(InputAction::MoveRight, STEP_MS + 5);
+        game.world_loop_contents(STEP_MS + 20);
+        assert_eq!(min_x(&game), x + 1);
+        assert_eq!(game.current_block.get_pieces()[0].y, y);
+    }
+
+    #[test]
+    fn soft_drop_lasts_from_press_to_release() {
+        let mut game = start_game();
+        game.press(InputAction::SoftDrop, STEP_MS + 10);
+        game.world_loop_contents(2 * STEP_MS);
+        assert!(game.soft_drop);
+        game.release(InputAction::SoftDrop, 2 * STEP_MS + 10);
+        game.world_loop_contents(3 * STEP_MS);
+        assert!(!game.soft_drop);
+    }
+
+    // a vertical long block in the rightmost column
+    fn long_block_at_right_wall(game: &mut Game) {
+        let width = game.config.width;
+        let block = blocks::new(0, 1, &objects::Point { x: 5, y: 10 }).get_rotated(1);
+        let dx = width - 1 - block.get_pieces()[0].x;
+        game.current_block = block.get_moved(dx, 0);
+    }
+
+    #[test]
+    fn rotation_at_the_wall_kicks_the_block_away_from_it() {
+        let mut game = start_game();
+        long_block_at_right_wall(&mut game);
+        game.apply_movement(objects::Movement::RotateRight);
+        let pieces = game.current_block.get_pieces();
+        assert!(pieces.iter().all(|point| point.y == pieces[0].y));
+        assert_eq!(
+            pieces.iter().map(|point| point.x).max(),
+            Some(game.config.width - 1)
+        );
+    }
+
+    #[test]
+    fn classic_rotation_refuses_to_turn_at_the_wall() {
+        let mut game = Game::default();
+        let config = GameConfig {
+            rotation: RotationSystem::Classic,
+            ..GameConfig::default()
+        };
+        game.set_state("test", 1, 5, config, Box::new(RecordingRenderer::new()));
+        long_block_at_right_wall(&mut game);
+        let before = game.current_block.get_pieces().clone();
+        game.apply_movement(objects::Movement::RotateRight);
+        assert_eq!(game.current_block.get_pieces(), &before);
+    }
+
+    // every block type in every rotation state, pushed as far as it goes in one direction
+    fn blocks_against_wall(game: &mut Game, dx: i32, dy: i32) -> Vec<blocks::Block> {
+        let mut result = Vec::new();
+        for rand in 0..blocks::NUM_BLOCK_TYPES {
+            for turns in 0..4 {
+                game.current_block =
+                    blocks::new(rand, 1, &objects::Point { x: 5, y: 8 }).get_rotated(turns);
+                while game.try_move(dx, dy) {}
+                result.push(game.current_block.clone());
             }
-            if all_pieces_ok {
-                for point in self.current_block.get_pieces_mut() {
-                    point.x += 1;
+        }
+        result
+    }
+
+    #[test]
+    fn every_block_can_be_rotated_at_every_wall() {
+        let mut game = Game::default();
+        let config = GameConfig {
+            width: 10,
+            height: 20,
+            ..GameConfig::default()
+        };
+        game.set_state("test", 1, 5, config, Box::new(RecordingRenderer::new()));
+        let (width, height) = (game.config.width, game.config.get_total_height());
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            for block in blocks_against_wall(&mut game, dx, dy) {
+                let pieces = block.get_pieces();
+                let touches_wall = match (dx, dy) {
+                    (-1, _) => pieces.iter().any(|point| point.x == 0),
+                    (1, _) => pieces.iter().any(|point| point.x == width - 1),
+                    (_, -1) => pieces.iter().any(|point| point.y == 0),
+                    _ => pieces.iter().any(|point| point.y == height - 1),
+                };
+                assert!(
+                    touches_wall,
+                    "{:?} stopped before the wall",
+                    block.get_block_type()
+                );
+                for turns in [-1, 1, 2] {
+                    game.current_block = block.clone();
+                    assert!(
+                        game.try_rotate(turns),
+                        "{:?} in rotation {} can't turn by {} at wall ({}, {})",
+                        block.get_block_type(),
+                        block.get_rotation(),
+                        turns,
+                        dx,
+                        dy
+                    );
+                    assert!(game.block_stack.as_ref().unwrap().fits(&game.current_block));
                 }
             }
         }
-        if self.movement == objects::Movement::RotateLeft
-            || self.movement == objects::Movement::RotateRight
-        {
-            let rotated = if self.movement == objects::Movement::RotateLeft {
-                &self.current_block.get_rotated_left()
-            } else {
-                &self.current_block.get_rotated_right()
+    }
+
+    #[test]
+    fn half_turn_key_turns_the_block_around() {
+        let mut game = start_game();
+        // a tee block
+        game.current_block = blocks::new(1, 1, &objects::Point { x: 5, y: 10 });
+        let rotation = game.current_block.get_rotation();
+        game.press(InputAction::Rotate180, STEP_MS + 10);
+        game.release(InputAction::Rotate180, STEP_MS + 20);
+        game.world_loop_contents(STEP_MS + 40);
+        assert_eq!(game.current_block.get_rotation(), (rotation + 2) % 4);
+    }
+
+    // 10 wide board, the lowest rows of the stack are given
+    fn game_with_stack(rows: &[&str]) -> (Game, RecordingRenderer) {
+        let renderer = RecordingRenderer::new();
+        let mut game = Game::default();
+        let config = GameConfig {
+            width: 10,
+            height: 20,
+            ..GameConfig::default()
+        };
+        game.set_state("test", 1, 5, config, Box::new(renderer.clone()));
+        let height = game.config.get_total_height() as usize;
+        let mut stack_rows = vec![".........."; height - rows.len()];
+        stack_rows.extend(rows);
+        game.block_stack = Some(BlockStack::from_rows(&stack_rows));
+        (game, renderer)
+    }
+
+    // a tee turned into a slot at the bottom, the last three rows are given
+    fn spin_into_slot(rows: [&str; 3]) -> (Game, RecordingRenderer) {
+        let (mut game, renderer) = game_with_stack(&rows);
+        let bottom = game.config.get_total_height() - 3;
+        let tee = blocks::new(1, 1, &objects::Point { x: 5, y: 0 }).get_rotated(-1);
+        let position = tee.get_position();
+        game.current_block = tee.get_moved(-position.x, bottom - position.y);
+        assert!(game.apply_movement(objects::Movement::RotateRight));
+        (game, renderer)
+    }
+
+    // hard drops a vertical long block in the given column, leaving out the points for the drop
+    fn drop_long_block(game: &mut Game, x: i32) {
+        let block = blocks::new(0, 1, &objects::Point { x: 5, y: 2 }).get_rotated(1);
+        game.current_block = block.get_moved(x - block.get_pieces()[0].x, 0);
+        let distance = game.get_drop_distance();
+        game.score -= distance * game.get_rules().get_hard_drop_points_per_row();
+        assert!(game.apply_movement(objects::Movement::HardDrop));
+    }
+
+    #[test]
+    fn tee_turned_into_a_slot_scores_a_t_spin() {
+        let (mut game, renderer) = spin_into_slot(["..#.......", "...#######", "#.########"]);
+        assert!(game.apply_movement(objects::Movement::HardDrop));
+        assert_eq!(game.lines, 2);
+        assert_eq!(game.score, 12);
+        assert!(renderer.get_calls().contains(&RenderCall::TSpin {
+            t_spin: TSpin::Full,
+            rows: 2
+        }));
+    }
+
+    #[test]
+    fn moving_after_the_turn_is_no_t_spin() {
+        let (mut game, renderer) = spin_into_slot(["#.#.......", "....######", "#..#######"]);
+        assert!(game.try_move(1, 0));
+        assert!(game.try_move(-1, 0));
+        assert!(game.apply_movement(objects::Movement::HardDrop));
+        assert_eq!(game.score, 0);
+        assert!(!renderer
+            .get_calls()
+            .iter()
+            .any(|call| matches!(call, RenderCall::TSpin { .. })));
+    }
+
+    #[test]
+    fn quads_in_a_row_are_back_to_back_and_an_empty_stack_is_a_perfect_clear() {
+        let (mut game, _renderer) = game_with_stack(&[".#########"; 8]);
+        drop_long_block(&mut game, 0);
+        assert_eq!(game.score, 8);
+        drop_long_block(&mut game, 0);
+        // back to back, continued combo and perfect clear
+        assert_eq!(game.score, 8 + 12 + 1 + 20);
+        assert_eq!(game.streaks.get_max_combo(), 2);
+        assert_eq!(game.streaks.get_back_to_backs(), 1);
+        assert_eq!(game.streaks.get_perfect_clears(), 1);
+    }
+
+    #[test]
+    fn clears_are_scored_by_the_configured_rules() {
+        let (mut game, _renderer) = game_with_stack(&[".#########"; 4]);
+        game.config.scoring = ScoringKind::Nes;
+        drop_long_block(&mut game, 0);
+        assert_eq!(game.score, 1200 * 2);
+    }
+
+    #[test]
+    fn vertical_long_block_at_the_left_wall_can_be_laid_flat() {
+        let mut game = start_game();
+        game.current_block = blocks::new(0, 1, &objects::Point { x: 5, y: 10 }).get_rotated(1);
+        while game.try_move(-1, 0) {}
+        assert!(game
+            .current_block
+            .get_pieces()
+            .iter()
+            .all(|point| point.x == 0));
+        game.apply_movement(objects::Movement::RotateLeft);
+        let pieces = game.current_block.get_pieces();
+        assert!(pieces.iter().all(|point| point.y == pieces[0].y));
+        assert_eq!(pieces.iter().map(|point| point.x).min(), Some(0));
+    }
+
+    #[test]
+    fn blocks_spawn_inside_the_narrowest_board() {
+        let mut game = Game::default();
+        let config = GameConfig {
+            width: MIN_BOARD_WIDTH,
+            ..GameConfig::default()
+        };
+        game.set_state("test", 1, 5, config, Box::new(RecordingRenderer::new()));
+        let spawn = game.config.get_spawn_point();
+        let block_stack = game.block_stack.as_ref().unwrap();
+        for rand in 0..blocks::NUM_BLOCK_TYPES {
+            assert!(block_stack.fits(&blocks::new(rand, 1, &spawn)));
+        }
+    }
+
+    #[test]
+    fn hard_drop_locks_the_block_at_the_bottom_and_scores_the_distance() {
+        let mut game = start_game();
+        let distance = game.get_drop_distance();
+        let landed = game.current_block.get_moved(0, distance);
+        let next_type = game.next_blocks[0].get_block_type();
+        game.press(InputAction::HardDrop, STEP_MS + 5);
+        game.world_loop_contents(STEP_MS + 20);
+        let block_stack = game.block_stack.as_ref().unwrap();
+        for point in landed.get_pieces() {
+            assert!(block_stack.is_occupied(point.x, point.y));
+        }
+        assert_eq!(game.current_block.get_block_type(), next_type);
+        assert_eq!(
+            game.score,
+            distance * game.get_rules().get_hard_drop_points_per_row()
+        );
+    }
+
+    #[test]
+    fn sonic_drop_moves_the_block_down_without_locking() {
+        let mut game = start_game();
+        let landed = game.current_block.get_moved(0, game.get_drop_distance());
+        game.press(InputAction::SonicDrop, STEP_MS + 5);
+        game.press(InputAction::MoveLeft, STEP_MS + 6);
+        game.world_loop_contents(STEP_MS + 20);
+        assert_eq!(
+            game.current_block.get_pieces(),
+            landed.get_moved(-1, 0).get_pieces()
+        );
+        assert!(game.block_stack.as_ref().unwrap().is_empty());
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn hold_swaps_the_current_block_once_per_drop() {
+        let mut game = start_game();
+        let first = game.current_block.get_block_type();
+        let second = game.next_blocks[0].get_block_type();
+        game.press(InputAction::Hold, STEP_MS + 5);
+        game.press(InputAction::Hold, STEP_MS + 6);
+        game.world_loop_contents(STEP_MS + 20);
+        assert_eq!(game.current_block.get_block_type(), second);
+        assert_eq!(
+            game.hold_block.as_ref().map(|block| block.get_block_type()),
+            Some(first)
+        );
+
+        game.press(InputAction::HardDrop, STEP_MS + 30);
+        game.press(InputAction::Hold, STEP_MS + 31);
+        game.world_loop_contents(STEP_MS + 50);
+        let spawned = blocks::new(
+            blocks::rand_for_block(&game.current_block),
+            game.level,
+            &game.config.get_spawn_point(),
+        );
+        assert_eq!(game.current_block.get_block_type(), first);
+        assert_eq!(game.current_block.get_pieces(), spawned.get_pieces());
+    }
+
+    #[test]
+    fn next_queue_has_the_configured_length_and_moves_up() {
+        for preview_count in 1..=MAX_PREVIEW_COUNT {
+            let mut game = Game::default();
+            let config = GameConfig {
+                preview_count,
+                ..GameConfig::default()
             };
-            let mut all_pieces_ok = true;
-            for point in rotated {
-                if !(point.x > 0 && point.x < GAME_WIDTH && point.y > 0 && point.y < GAME_HEIGHT) {
-                    all_pieces_ok = false;
-                }
-                if all_pieces_ok {
-                    all_pieces_ok = block_stack.check_collision(point);
-                }
+            game.set_state("test", 1, 5, config, Box::new(RecordingRenderer::new()));
+            game.world_loop_contents(STEP_MS);
+            assert_eq!(game.next_blocks.len(), preview_count as usize);
+            let queued: Vec<blocks::BlockType> = game
+                .next_blocks
+                .iter()
+                .map(|block| block.get_block_type())
+                .collect();
+            game.press(InputAction::HardDrop, STEP_MS + 5);
+            game.world_loop_contents(STEP_MS + 20);
+            assert_eq!(game.current_block.get_block_type(), queued[0]);
+            assert_eq!(game.next_blocks.len(), preview_count as usize);
+            for (index, block_type) in queued.iter().enumerate().skip(1) {
+                assert_eq!(game.next_blocks[index - 1].get_block_type(), *block_type);
             }
-            if all_pieces_ok {
-                self.current_block.apply_rotated(rotated);
+        }
+    }
+
+    #[test]
+    fn landed_block_locks_only_after_the_lock_delay() {
+        let mut game = start_game();
+        let delay = game.config.lock_delay;
+        game.press(InputAction::SonicDrop, STEP_MS + 5);
+        game.world_loop_contents(STEP_MS + 20);
+        game.world_loop_contents(STEP_MS + delay - 20);
+        // moving restarts the delay
+        game.press(InputAction::MoveLeft, STEP_MS + delay - 10);
+        game.release(InputAction::MoveLeft, STEP_MS + delay - 5);
+        game.world_loop_contents(STEP_MS + delay + 100);
+        assert!(game.block_stack.as_ref().unwrap().is_empty());
+        game.world_loop_contents(STEP_MS + 2 * delay + 100);
+        assert!(!game.block_stack.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn held_direction_keeps_moving_after_the_auto_shift_delay() {
+        let mut game = start_game();
+        let x = min_x(&game);
+        let (das, arr) = (game.config.das, game.config.arr);
+        game.press(InputAction::MoveLeft, STEP_MS + 1);
+        game.world_loop_contents(STEP_MS + das - 20);
+        assert_eq!(min_x(&game), x - 1);
+        // a few ms more than the delay and two repeats, ticks are 1/60 s apart
+        game.world_loop_contents(STEP_MS + das + 2 * arr + 40);
+        assert_eq!(min_x(&game), x - 4);
+        game.release(InputAction::MoveLeft, STEP_MS + das + 2 * arr + 50);
+        game.world_loop_contents(STEP_MS + das + 10 * arr);
+        assert_eq!(min_x(&game), x - 4);
+    }
+
+    #[test]
+    fn auto_shift_charge_carries_over_to_the_next_block() {
+        let mut game = start_game();
+        let das = game.config.das;
+        game.press(InputAction::MoveRight, STEP_MS + 1);
+        game.world_loop_contents(STEP_MS + 2 * das);
+        let spawned_x = game.next_blocks[0]
+            .get_pieces()
+            .iter()
+            .map(|point| point.x)
+            .min()
+            .unwrap();
+        game.press(InputAction::HardDrop, STEP_MS + 2 * das + 5);
+        // less than the delay, the new block only moves because the charge was kept
+        game.world_loop_contents(STEP_MS + 3 * das - 20);
+        assert!(min_x(&game) >= spawned_x + 2);
+    }
+
+    // game at a level where gravity is faster than the old fixed soft drop speed
+    fn start_fast_game(soft_drop_factor: u32) -> Game {
+        let mut game = Game::default();
+        let config = GameConfig {
+            soft_drop_factor,
+            ..GameConfig::default()
+        };
+        game.set_state("test", 9, 5, config, Box::new(RecordingRenderer::new()));
+        game.world_loop_contents(STEP_MS);
+        game
+    }
+
+    #[test]
+    fn high_levels_can_be_started_and_fall_at_20g() {
+        let mut game = Game::default();
+        let config = GameConfig {
+            height: 20,
+            ..GameConfig::default()
+        };
+        game.set_state("test", 25, 5, config, Box::new(RecordingRenderer::new()));
+        assert_eq!(game.level, 25);
+        game.world_loop_contents(STEP_MS);
+        // the first frame takes the block to the floor
+        game.world_loop_contents(STEP_MS + 17);
+        assert_eq!(game.get_drop_distance(), 0);
+
+        game.set_state(
+            "test",
+            MAX_START_LEVEL + 1,
+            5,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        assert_eq!(game.level, 1);
+    }
+
+    #[test]
+    fn fractional_gravity_adds_up_over_frames() {
+        let mut game = start_game();
+        let y = game.current_block.get_pieces()[0].y;
+        // level 1 of the legacy curve falls a row every 450 ms, 27 frames
+        game.world_loop_contents(STEP_MS + 440);
+        assert_eq!(game.current_block.get_pieces()[0].y, y);
+        game.world_loop_contents(STEP_MS + 460);
+        assert_eq!(game.current_block.get_pieces()[0].y, y + 1);
+    }
+
+    #[test]
+    fn soft_drop_multiplies_gravity_and_scores_each_row() {
+        let mut game = start_fast_game(DEFAULT_SOFT_DROP_FACTOR);
+        let y = game.current_block.get_lowest_row();
+        game.world_loop_contents(STEP_MS + 200);
+        let rows_without = game.current_block.get_lowest_row() - y;
+        game.press(InputAction::SoftDrop, STEP_MS + 201);
+        game.world_loop_contents(STEP_MS + 400);
+        let rows_with = game.current_block.get_lowest_row() - y - rows_without;
+        assert!(
+            rows_with > 2 * rows_without,
+            "{} vs {}",
+            rows_with,
+            rows_without
+        );
+        assert_eq!(
+            game.score,
+            rows_with * game.get_rules().get_soft_drop_points_per_row()
+        );
+    }
+
+    #[test]
+    fn instant_soft_drop_goes_to_the_bottom_without_locking() {
+        let mut game = start_fast_game(0);
+        let distance = game.get_drop_distance();
+        game.press(InputAction::SoftDrop, STEP_MS + 1);
+        game.world_loop_contents(STEP_MS + 20);
+        assert_eq!(game.get_drop_distance(), 0);
+        assert_eq!(
+            game.score,
+            distance * game.get_rules().get_soft_drop_points_per_row()
+        );
+        assert!(game.block_stack.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn line_clear_delay_shows_the_rows_before_removing_them() {
+        let (mut game, renderer) = game_with_stack(&[".#########"; 4]);
+        game.config.line_clear_delay = 200;
+        game.world_loop_contents(STEP_MS);
+        drop_long_block(&mut game, 0);
+        assert_eq!(game.lines, 4);
+        game.world_loop_contents(STEP_MS + 100);
+        assert!(game.current_block.get_pieces().is_empty());
+        match renderer.get_frames().last() {
+            Some(RenderCall::Frame {
+                clearing_rows,
+                stack,
+                ..
+            }) => {
+                assert_eq!(clearing_rows, &vec![16, 17, 18, 19]);
+                assert_eq!(stack.len(), 40);
             }
+            _ => panic!("no frame"),
+        }
+        game.world_loop_contents(STEP_MS + 250);
+        assert!(game.block_stack.as_ref().unwrap().is_empty());
+        assert!(!game.current_block.get_pieces().is_empty());
+    }
+
+    #[test]
+    fn key_held_through_the_entry_delay_moves_the_next_block_at_the_repeat_rate() {
+        let (mut game, _renderer) = game_with_stack(&[]);
+        game.config.entry_delay = 300;
+        game.world_loop_contents(STEP_MS);
+        game.press(InputAction::HardDrop, STEP_MS + 1);
+        game.press(InputAction::MoveRight, STEP_MS + 2);
+        let mut timestamp = STEP_MS + 17;
+        game.world_loop_contents(timestamp);
+        let spawn_x = game.next_blocks[0]
+            .get_pieces()
+            .iter()
+            .map(|point| point.x)
+            .min()
+            .unwrap();
+        while game.current_block.get_pieces().is_empty() {
+            timestamp += 17;
+            game.world_loop_contents(timestamp);
+        }
+        // no repeats piled up during the delay
+        assert!(min_x(&game) <= spawn_x + 1);
+        // still charged, the next repeat follows within the repeat rate
+        let x = min_x(&game);
+        game.world_loop_contents(timestamp + 60);
+        assert_eq!(min_x(&game), x + 1);
+    }
+
+    #[test]
+    fn rotation_and_hold_during_the_entry_delay_apply_to_the_next_block() {
+        let (mut game, _renderer) = game_with_stack(&[]);
+        game.config.entry_delay = 100;
+        game.world_loop_contents(STEP_MS);
+        game.press(InputAction::HardDrop, STEP_MS + 1);
+        game.world_loop_contents(STEP_MS + 20);
+        assert!(game.current_block.get_pieces().is_empty());
+        let next = game.next_blocks[0].get_block_type();
+        let after_next = game.next_blocks[1].get_block_type();
+        game.press(InputAction::RotateRight, STEP_MS + 30);
+        game.press(InputAction::Hold, STEP_MS + 40);
+        game.world_loop_contents(STEP_MS + 50);
+        assert!(game.current_block.get_pieces().is_empty());
+        game.world_loop_contents(STEP_MS + 150);
+        assert_eq!(game.hold_block.as_ref().unwrap().get_block_type(), next);
+        assert_eq!(game.current_block.get_block_type(), after_next);
+        assert_eq!(game.current_block.get_rotation(), 1);
+    }
+
+    #[test]
+    fn whole_game_runs_without_a_browser() {
+        let mut game = start_game();
+        let mut timestamp = STEP_MS;
+        while !game.is_over() && timestamp < 1000 * STEP_MS {
+            timestamp += STEP_MS;
+            game.world_loop_contents(timestamp);
+        }
+        assert!(game.is_over());
+        assert!(game
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::GameOver(result) if result.name == "test")));
+    }
+
+    fn game_with_board(width: i32, height: i32, hidden_rows: i32) -> Game {
+        let config = GameConfig {
+            width,
+            height,
+            hidden_rows,
+            ..GameConfig::default()
+        };
+        let mut game = Game::default();
+        game.set_state("test", 1, 42, config, Box::new(RecordingRenderer::new()));
+        game
+    }
+
+    #[test]
+    fn blocks_spawn_in_the_middle_column_above_the_visible_rows() {
+        let spawn = |width, height, hidden_rows| {
+            let point = game_with_board(width, height, hidden_rows)
+                .get_config()
+                .get_spawn_point();
+            (point.x, point.y)
+        };
+        assert_eq!(spawn(10, 20, 0), (5, 0));
+        assert_eq!(spawn(10, 20, 4), (5, 2));
+        assert_eq!(spawn(40, 20, 0), (20, 0));
+        let game = game_with_board(40, 20, 0);
+        assert!(game
+            .current_block
+            .get_pieces()
+            .iter()
+            .any(|point| point.x == 20 && point.y == 0));
+    }
+
+    #[test]
+    fn blocks_stop_at_the_right_and_bottom_edges_of_the_board() {
+        let mut game = game_with_board(10, 20, 0);
+        let mut timestamp = STEP_MS;
+        while game.block_stack.as_ref().unwrap().is_empty() {
+            game.press(InputAction::MoveRight, timestamp + 1);
+            game.release(InputAction::MoveRight, timestamp + 2);
+            timestamp += STEP_MS;
+            game.world_loop_contents(timestamp);
+            assert!(game
+                .current_block
+                .get_pieces()
+                .iter()
+                .all(|point| point.x < 10));
         }
-        return true;
+        let stack = game.block_stack.as_ref().unwrap();
+        assert_eq!(stack.get_cells().map(|(point, _)| point.x).max(), Some(9));
+        assert_eq!(stack.get_cells().map(|(point, _)| point.y).max(), Some(19));
     }
 }
@@ -0,0 +1,169 @@
+use crate::game::TICKS_PER_SECOND;
+
+// Gravity is given in cells per frame (simulation step), in fractions of a cell.
+pub const SUBCELLS_PER_CELL: u32 = 1 << 16;
+// the strongest gravity of the guideline, the block reaches the floor of a 20 row board in one frame
+pub const GRAVITY_20G: u32 = 20 * SUBCELLS_PER_CELL;
+
+// One row every `ms` milliseconds. Rounded up, so the row is reached after exactly that many frames.
+const fn ms_per_row(ms: u32) -> u32 {
+    (SUBCELLS_PER_CELL as u64 * 1000).div_ceil(ms as u64 * TICKS_PER_SECOND) as u32
+}
+
+// one row every `frames` frames
+const fn frames_per_row(frames: u32) -> u32 {
+    SUBCELLS_PER_CELL.div_ceil(frames)
+}
+
+// `cells` rows every frame
+const fn rows_per_frame(cells: u32) -> u32 {
+    cells * SUBCELLS_PER_CELL
+}
+
+// Tables by level, starting at level 0. Levels past the end keep the last entry.
+
+// 500 ms per row at level 0, 50 ms less per level up to level 9 as this game always had,
+// then on to 20G at level 20
+const LEGACY: [u32; 21] = [
+    ms_per_row(500),
+    ms_per_row(450),
+    ms_per_row(400),
+    ms_per_row(350),
+    ms_per_row(300),
+    ms_per_row(250),
+    ms_per_row(200),
+    ms_per_row(150),
+    ms_per_row(100),
+    ms_per_row(50),
+    ms_per_row(40),
+    ms_per_row(33),
+    ms_per_row(27),
+    ms_per_row(22),
+    rows_per_frame(1),
+    rows_per_frame(2),
+    rows_per_frame(3),
+    rows_per_frame(5),
+    rows_per_frame(10),
+    rows_per_frame(15),
+    GRAVITY_20G,
+];
+
+// frames per row of the NES version, one row per frame from level 29 on
+const NES: [u32; 30] = [
+    frames_per_row(48),
+    frames_per_row(43),
+    frames_per_row(38),
+    frames_per_row(33),
+    frames_per_row(28),
+    frames_per_row(23),
+    frames_per_row(18),
+    frames_per_row(13),
+    frames_per_row(8),
+    frames_per_row(6),
+    frames_per_row(5),
+    frames_per_row(5),
+    frames_per_row(5),
+    frames_per_row(4),
+    frames_per_row(4),
+    frames_per_row(4),
+    frames_per_row(3),
+    frames_per_row(3),
+    frames_per_row(3),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(2),
+    frames_per_row(1),
+];
+
+// (0.8 - (level - 1) * 0.007) ^ (level - 1) seconds per row up to level 15, level 0 as level 1,
+// then on to 20G at level 20
+const GUIDELINE: [u32; 21] = [
+    ms_per_row(1000),
+    ms_per_row(1000),
+    ms_per_row(793),
+    ms_per_row(618),
+    ms_per_row(473),
+    ms_per_row(355),
+    ms_per_row(262),
+    ms_per_row(190),
+    ms_per_row(135),
+    ms_per_row(94),
+    ms_per_row(64),
+    ms_per_row(43),
+    ms_per_row(28),
+    ms_per_row(18),
+    ms_per_row(11),
+    ms_per_row(7),
+    rows_per_frame(3),
+    rows_per_frame(5),
+    rows_per_frame(10),
+    rows_per_frame(15),
+    GRAVITY_20G,
+];
+
+// how fast blocks fall at each level
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GravityCurve {
+    Legacy,
+    Nes,
+    Guideline,
+}
+
+impl GravityCurve {
+    // in fractions of a cell per frame, see `SUBCELLS_PER_CELL`
+    pub fn get_gravity(&self, level: i32) -> u32 {
+        let table: &[u32] = match self {
+            GravityCurve::Legacy => &LEGACY,
+            GravityCurve::Nes => &NES,
+            GravityCurve::Guideline => &GUIDELINE,
+        };
+        table[(level.max(0) as usize).min(table.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_get_faster_with_every_level_up_to_their_maximum() {
+        for curve in [
+            GravityCurve::Legacy,
+            GravityCurve::Nes,
+            GravityCurve::Guideline,
+        ] {
+            for level in 1..40 {
+                assert!(
+                    curve.get_gravity(level) >= curve.get_gravity(level - 1),
+                    "{:?} slows down at level {}",
+                    curve,
+                    level
+                );
+            }
+        }
+        assert_eq!(GravityCurve::Legacy.get_gravity(30), GRAVITY_20G);
+        assert_eq!(GravityCurve::Guideline.get_gravity(30), GRAVITY_20G);
+        assert_eq!(GravityCurve::Nes.get_gravity(30), SUBCELLS_PER_CELL);
+    }
+
+    #[test]
+    fn low_levels_fall_by_fractions_of_a_cell() {
+        // a row after exactly this many frames at level 0
+        for (curve, frames) in [
+            (GravityCurve::Legacy, 30),
+            (GravityCurve::Nes, 48),
+            (GravityCurve::Guideline, 60),
+        ] {
+            let gravity = curve.get_gravity(0);
+            assert!(gravity * frames >= SUBCELLS_PER_CELL);
+            assert!(gravity * (frames - 1) < SUBCELLS_PER_CELL);
+        }
+    }
+}
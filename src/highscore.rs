@@ -1,15 +1,19 @@
+use crate::game::GameResult;
+use crate::scoring::ScoringKind;
 use crate::utils::format_duration;
 use crate::utils::log;
 use chrono::offset::Local;
 use core::cmp::Ordering;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use wasm_bindgen::prelude::*;
 
 const STORAGE_KEY: &str = "highscore";
+// per scoring ruleset, their scores don't compare
 const MAX_ENTRIES: usize = 20;
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Ord)]
+#[derive(Serialize, Deserialize, Eq, PartialEq)]
 struct HighscoreEntry {
     name: String,
     score: i32,
@@ -17,6 +21,18 @@ struct HighscoreEntry {
     level: i32,
     #[serde(default = "default_duration")]
     duration: u32,
+    #[serde(default)]
+    seed: Option<u64>,
+    // name of the scoring rules, entries from before they could be chosen used the legacy ones
+    #[serde(default = "default_scoring")]
+    scoring: String,
+    // entries from before these were tracked have 0
+    #[serde(default)]
+    max_combo: u32,
+    #[serde(default)]
+    back_to_backs: u32,
+    #[serde(default)]
+    perfect_clears: u32,
     time: String,
 }
 
@@ -24,120 +40,141 @@ fn default_duration() -> u32 {
     (99 * 60 + 59) * 1000
 }
 
-impl PartialOrd for HighscoreEntry {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+fn default_scoring() -> String {
+    ScoringKind::Legacy.get_name().to_string()
+}
+
+impl HighscoreEntry {
+    // position of the ruleset in the table, rules no longer known last
+    fn get_scoring_order(&self) -> usize {
+        ScoringKind::from_name(&self.scoring)
+            .and_then(|kind| ScoringKind::ALL.iter().position(|known| *known == kind))
+            .unwrap_or(ScoringKind::ALL.len())
+    }
+}
+
+// grouped by scoring ruleset, the best entry of each group first
+impl Ord for HighscoreEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.scoring != other.scoring {
+            return (self.get_scoring_order(), &self.scoring)
+                .cmp(&(other.get_scoring_order(), &other.scoring));
+        }
         if self.score == other.score {
             if self.lines == other.lines {
                 if self.level == other.level {
                     if self.duration == other.duration {
-                        return Some(other.time.cmp(&self.time));
+                        return other.time.cmp(&self.time);
                     }
                     // for duration the lower value is ranked higher
-                    return Some(self.duration.cmp(&other.duration));
+                    return self.duration.cmp(&other.duration);
                 }
-                return Some(other.level.cmp(&self.level));
+                return other.level.cmp(&self.level);
             }
-            return Some(other.lines.cmp(&self.lines));
+            return other.lines.cmp(&self.lines);
         }
-        return Some(other.score.cmp(&self.score));
+        other.score.cmp(&self.score)
     }
 }
 
-pub fn add_score(name: &str, level: i32, lines: i32, score: i32, duration: u32) -> Option<String> {
-    let window = web_sys::window().unwrap();
-    let local_storage_opt = window.local_storage().unwrap();
-    if local_storage_opt.is_some() {
-        let local_storage = local_storage_opt.unwrap();
-        let json_result = local_storage.get_item(STORAGE_KEY);
-        let mut entries: Vec<HighscoreEntry>;
-        if json_result.is_ok() {
-            let json_opt = json_result.unwrap();
-            if json_opt.is_some() {
-                let json = json_opt.unwrap();
-                let entries_result = serde_json::from_str(&json);
-                if entries_result.is_ok() {
-                    entries = entries_result.unwrap();
-                } else {
-                    entries = Vec::new();
-                }
-            } else {
-                entries = Vec::new();
-            }
-        } else {
-            entries = Vec::new();
+impl PartialOrd for HighscoreEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// sorts and keeps the best `MAX_ENTRIES` of each scoring ruleset
+fn keep_best(entries: &mut Vec<HighscoreEntry>) {
+    entries.sort();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    entries.retain(|entry| {
+        let count = counts.entry(entry.scoring.clone()).or_default();
+        *count += 1;
+        *count <= MAX_ENTRIES
+    });
+}
+
+fn load_entries(local_storage: &web_sys::Storage) -> Vec<HighscoreEntry> {
+    if let Ok(Some(json)) = local_storage.get_item(STORAGE_KEY) {
+        if let Ok(entries) = serde_json::from_str(&json) {
+            return entries;
         }
+    }
+    Vec::new()
+}
+
+pub fn add_score(result: &GameResult) -> Option<String> {
+    let window = web_sys::window().unwrap();
+    if let Some(local_storage) = window.local_storage().unwrap() {
+        let mut entries = load_entries(&local_storage);
 
         let new_entry = HighscoreEntry {
-            name: name.to_string(),
-            score: score,
-            lines: lines,
-            level: level,
-            duration: duration,
+            name: result.name.clone(),
+            score: result.score,
+            lines: result.lines,
+            level: result.level,
+            duration: result.duration,
+            seed: Some(result.seed),
+            scoring: result.scoring.get_name().to_string(),
+            max_combo: result.max_combo,
+            back_to_backs: result.back_to_backs,
+            perfect_clears: result.perfect_clears,
             time: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         };
         let new_entry_time = new_entry.time.clone();
         entries.push(new_entry);
-        entries.sort();
-        while entries.len() > MAX_ENTRIES {
-            entries.pop();
-        }
+        keep_best(&mut entries);
         let json = serde_json::to_string(&entries).unwrap();
         let result = local_storage.set_item(STORAGE_KEY, &json);
-        if result.is_err() {
+        if let Err(error) = result {
             log!(
                 "could not save highscore to local_storage: {}",
-                result.err().unwrap().as_string().unwrap()
+                error.as_string().unwrap()
             );
         } else {
             return Some(new_entry_time);
         }
     }
-    return None;
+    None
 }
 
-pub fn print_highscores(latest_timestamp: Option<String>) {
+pub fn print_highscores(id_prefix: &str, latest_timestamp: Option<String>) {
     let window = web_sys::window().unwrap();
-    let local_storage_opt = window.local_storage().unwrap();
-    if local_storage_opt.is_some() {
-        let local_storage = local_storage_opt.unwrap();
+    if let Some(local_storage) = window.local_storage().unwrap() {
         let document = window.document().unwrap();
         let table = document
-            .get_element_by_id("highscores-table")
+            .get_element_by_id(&format!("{}highscores-table", id_prefix))
             .unwrap()
             .dyn_into::<web_sys::HtmlElement>()
             .unwrap();
         while table.child_element_count() > 1 {
             table.last_element_child().unwrap().remove();
         }
-        let json_result = local_storage.get_item(STORAGE_KEY);
-        let entries: Vec<HighscoreEntry>;
-        if json_result.is_ok() {
-            let json_opt = json_result.unwrap();
-            if json_opt.is_some() {
-                let json = json_opt.unwrap();
-                let entries_result = serde_json::from_str(&json);
-                if entries_result.is_ok() {
-                    entries = entries_result.unwrap();
-                    let mut i = 0;
-                    for entry in entries {
-                        let result = print_entry(
-                            &document,
-                            &table,
-                            &entry,
-                            i + 1,
-                            latest_timestamp.is_some()
-                                && latest_timestamp.as_ref().unwrap() == &entry.time,
-                        );
-                        if result.is_err() {
-                            log!(
-                                "could not crate highscore table elements: {}",
-                                result.err().unwrap().as_string().unwrap()
-                            );
-                            break;
-                        }
-                        i += 1;
-                    }
-                }
+        let mut entries = load_entries(&local_storage);
+        // lists stored before they were kept per ruleset are mixed
+        keep_best(&mut entries);
+        let mut rank = 0;
+        let mut previous_scoring = None;
+        for entry in &entries {
+            // ranks count within each ruleset
+            if previous_scoring != Some(&entry.scoring) {
+                rank = 0;
+                previous_scoring = Some(&entry.scoring);
+            }
+            rank += 1;
+            let result = print_entry(
+                &document,
+                &table,
+                entry,
+                rank,
+                latest_timestamp.as_ref() == Some(&entry.time),
+            );
+            if let Err(error) = result {
+                log!(
+                    "could not crate highscore table elements: {}",
+                    error.as_string().unwrap()
+                );
+                break;
             }
         }
     }
@@ -160,6 +197,11 @@ fn print_entry(
     let td_lines = document.create_element("td").unwrap();
     let td_level = document.create_element("td").unwrap();
     let td_duration = document.create_element("td").unwrap();
+    let td_scoring = document.create_element("td").unwrap();
+    let td_max_combo = document.create_element("td").unwrap();
+    let td_back_to_backs = document.create_element("td").unwrap();
+    let td_perfect_clears = document.create_element("td").unwrap();
+    let td_seed = document.create_element("td").unwrap();
     let td_time = document.create_element("td").unwrap();
     tr.append_child(&td_rank)?;
     tr.append_child(&td_name)?;
@@ -167,6 +209,11 @@ fn print_entry(
     tr.append_child(&td_lines)?;
     tr.append_child(&td_level)?;
     tr.append_child(&td_duration)?;
+    tr.append_child(&td_scoring)?;
+    tr.append_child(&td_max_combo)?;
+    tr.append_child(&td_back_to_backs)?;
+    tr.append_child(&td_perfect_clears)?;
+    tr.append_child(&td_seed)?;
     tr.append_child(&td_time)?;
     td_rank.set_text_content(Some(&rank.to_string()));
     td_name.set_text_content(Some(&entry.name));
@@ -174,6 +221,48 @@ fn print_entry(
     td_lines.set_text_content(Some(&entry.lines.to_string()));
     td_level.set_text_content(Some(&entry.level.to_string()));
     td_duration.set_text_content(Some(&(format_duration(entry.duration)).to_string()));
+    td_scoring.set_text_content(Some(&entry.scoring));
+    td_max_combo.set_text_content(Some(&entry.max_combo.to_string()));
+    td_back_to_backs.set_text_content(Some(&entry.back_to_backs.to_string()));
+    td_perfect_clears.set_text_content(Some(&entry.perfect_clears.to_string()));
+    td_seed.set_text_content(entry.seed.map(|seed| seed.to_string()).as_deref());
     td_time.set_text_content(Some(&entry.time));
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(scoring: ScoringKind, score: i32) -> HighscoreEntry {
+        HighscoreEntry {
+            name: "test".to_string(),
+            score,
+            lines: 0,
+            level: 1,
+            duration: 0,
+            seed: None,
+            scoring: scoring.get_name().to_string(),
+            max_combo: 0,
+            back_to_backs: 0,
+            perfect_clears: 0,
+            time: String::new(),
+        }
+    }
+
+    #[test]
+    fn high_scores_of_one_ruleset_dont_push_out_another() {
+        let mut entries: Vec<HighscoreEntry> = (0..30)
+            .map(|score| entry(ScoringKind::Nes, 10000 + score))
+            .collect();
+        entries.push(entry(ScoringKind::Legacy, 5));
+        entries.push(entry(ScoringKind::Legacy, 7));
+        keep_best(&mut entries);
+        assert_eq!(entries.len(), MAX_ENTRIES + 2);
+        // legacy first, best first
+        assert_eq!(entries[0].score, 7);
+        assert_eq!(entries[1].score, 5);
+        assert_eq!(entries[2].score, 10029);
+        assert_eq!(entries.last().unwrap().score, 10010);
+    }
+}
@@ -0,0 +1,33 @@
+// what the player wants to do, independent of the key or button used for it
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    // straight down and lock immediately
+    HardDrop,
+    // straight down, but the block can still be moved
+    SonicDrop,
+    // swap the current block with the one kept aside
+    Hold,
+    RotateLeft,
+    RotateRight,
+    Rotate180,
+    Pause,
+    DebugInfo,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum InputState {
+    Pressed,
+    Released,
+}
+
+// a single press or release, queued by the front-end and consumed by the game in order
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct InputEvent {
+    pub action: InputAction,
+    pub state: InputState,
+    // front-end time in ms, the game applies the event in the tick it falls into
+    pub timestamp: u32,
+}
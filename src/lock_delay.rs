@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+// what gives a block that touched down more time before it locks
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LockReset {
+    // every move or rotation restarts the delay, up to a number of times per block
+    Move,
+    // only falling to a row the block has not reached before restarts the delay
+    Step,
+    // every move or rotation restarts the delay, without limit
+    Infinite,
+}
+
+impl LockReset {
+    pub const ALL: [LockReset; 3] = [LockReset::Move, LockReset::Step, LockReset::Infinite];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            LockReset::Move => "move",
+            LockReset::Step => "step",
+            LockReset::Infinite => "infinite",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LockReset> {
+        LockReset::ALL
+            .into_iter()
+            .find(|reset| reset.get_name() == name)
+    }
+}
+
+// Time a block may rest on the stack or the floor before it locks.
+// All times are game time in ms.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LockDelay {
+    delay: u64,
+    reset: LockReset,
+    max_resets: u32,
+    // when the block touched down, None while it is in the air
+    touch_time: Option<u64>,
+    // moves and rotations that restarted the delay for the current block
+    resets: u32,
+    // lowest row the current block reached, rows count downwards
+    lowest_row: i32,
+}
+
+impl LockDelay {
+    pub const fn new(delay: u32, reset: LockReset, max_resets: u32) -> Self {
+        Self {
+            delay: delay as u64,
+            reset,
+            max_resets,
+            touch_time: None,
+            resets: 0,
+            lowest_row: i32::MIN,
+        }
+    }
+
+    // a new block comes in
+    pub fn start_block(&mut self) {
+        self.touch_time = None;
+        self.resets = 0;
+        self.lowest_row = i32::MIN;
+    }
+
+    // the block moved down, its bottom is now at `row`
+    pub fn fell_to(&mut self, row: i32) {
+        if row > self.lowest_row {
+            self.lowest_row = row;
+            self.resets = 0;
+            self.touch_time = None;
+        }
+    }
+
+    // the block was moved or rotated successfully
+    pub fn moved(&mut self, time: u64) {
+        let may_reset = match self.reset {
+            LockReset::Move => self.resets < self.max_resets,
+            LockReset::Step => false,
+            LockReset::Infinite => true,
+        };
+        if may_reset && self.touch_time.is_some() {
+            self.resets += 1;
+            self.touch_time = Some(time);
+        }
+    }
+
+    // Called every tick with whether the block rests on something.
+    // Returns true if it has rested long enough to lock.
+    pub fn update(&mut self, time: u64, grounded: bool) -> bool {
+        if !grounded {
+            // e.g. moved over an edge, the delay starts again when it touches down
+            self.touch_time = None;
+            return false;
+        }
+        let touch_time = *self.touch_time.get_or_insert(time);
+        time - touch_time >= self.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grounded_at(reset: LockReset) -> LockDelay {
+        let mut lock_delay = LockDelay::new(500, reset, 2);
+        lock_delay.fell_to(10);
+        assert!(!lock_delay.update(1000, true));
+        lock_delay
+    }
+
+    #[test]
+    fn block_locks_after_resting_for_the_delay() {
+        let mut lock_delay = grounded_at(LockReset::Move);
+        assert!(!lock_delay.update(1499, true));
+        assert!(lock_delay.update(1500, true));
+    }
+
+    #[test]
+    fn moves_restart_the_delay_up_to_the_limit() {
+        let mut lock_delay = grounded_at(LockReset::Move);
+        lock_delay.moved(1400);
+        assert!(!lock_delay.update(1800, true));
+        lock_delay.moved(1800);
+        assert!(!lock_delay.update(2200, true));
+        // limit of 2 reached
+        lock_delay.moved(2200);
+        assert!(lock_delay.update(2300, true));
+    }
+
+    #[test]
+    fn step_reset_ignores_moves_but_not_falling_further() {
+        let mut lock_delay = grounded_at(LockReset::Step);
+        lock_delay.moved(1400);
+        assert!(lock_delay.update(1500, true));
+
+        let mut lock_delay = grounded_at(LockReset::Step);
+        lock_delay.fell_to(11);
+        assert!(!lock_delay.update(1400, true));
+        assert!(lock_delay.update(1900, true));
+    }
+
+    #[test]
+    fn infinite_reset_never_runs_out() {
+        let mut lock_delay = grounded_at(LockReset::Infinite);
+        for step in 1..100 {
+            lock_delay.moved(1000 + step * 400);
+            assert!(!lock_delay.update(1000 + step * 400 + 100, true));
+        }
+    }
+
+    #[test]
+    fn lock_reset_names_round_trip() {
+        for reset in LockReset::ALL {
+            assert_eq!(LockReset::from_name(reset.get_name()), Some(reset));
+        }
+        assert_eq!(LockReset::from_name("unknown"), None);
+    }
+}
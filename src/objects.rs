@@ -1,7 +1,8 @@
 use crate::block_stack;
 use crate::blocks;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -9,15 +10,24 @@ pub struct Point {
 
 #[derive(PartialEq)]
 pub enum Movement {
-    NONE,
-    LEFT,
-    RIGHT,
+    None,
+    Left,
+    Right,
     RotateLeft,
     RotateRight,
+    Rotate180,
+    HardDrop,
+    SonicDrop,
+    Hold,
 }
 
 pub struct DrawState<'draw_run> {
     pub current_block: &'draw_run blocks::Block,
-    pub next_block: &'draw_run blocks::Block,
+    // current block moved to where it would land, if the game shows it
+    pub ghost_block: Option<&'draw_run blocks::Block>,
     pub block_stack: &'draw_run block_stack::BlockStack,
+    // complete rows shown during the line clear delay, still part of the stack
+    pub clearing_rows: &'draw_run [i32],
+    // how far the line clear delay has run, from 0 to 1
+    pub clear_progress: f64,
 }
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+// Seedable pseudo random number generator (SplitMix64).
+// The same seed always yields the same sequence of blocks, which makes games reproducible.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Random {
+    seed: u64,
+    state: u64,
+}
+
+impl Random {
+    pub const fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // random number in min..max, max excluded
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        let span = (max - min) as u64;
+        min + (self.next_u64() % span) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(seed: u64) -> Vec<i32> {
+        let mut random = Random::new(seed);
+        (0..20).map(|_| random.range(0, 7)).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        assert_eq!(sequence(42), sequence(42));
+        assert_eq!(Random::new(42).get_seed(), 42);
+    }
+
+    #[test]
+    fn different_seeds_give_different_sequences() {
+        assert_ne!(sequence(1), sequence(2));
+    }
+
+    #[test]
+    fn range_stays_within_bounds() {
+        let mut random = Random::new(7);
+        for _ in 0..1000 {
+            let value = random.range(3, 10);
+            assert!((3..10).contains(&value));
+        }
+    }
+}
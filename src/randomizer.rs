@@ -0,0 +1,230 @@
+use crate::blocks;
+use crate::random::Random;
+use serde::{Deserialize, Serialize};
+
+const HISTORY_SIZE: usize = 4;
+const HISTORY_RETRIES: usize = 4;
+
+// strategy used to pick the next block
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RandomizerKind {
+    // every block type with the same probability, independent of the previous ones
+    Uniform,
+    // all 7 block types shuffled, dealt out, then reshuffled
+    Bag7,
+    // two of each block type shuffled, dealt out, then reshuffled
+    Bag14,
+    // uniform, but re-rolls a few times if the block was among the last ones dealt
+    History,
+}
+
+impl RandomizerKind {
+    pub const ALL: [RandomizerKind; 4] = [
+        RandomizerKind::Uniform,
+        RandomizerKind::Bag7,
+        RandomizerKind::Bag14,
+        RandomizerKind::History,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            RandomizerKind::Uniform => "uniform",
+            RandomizerKind::Bag7 => "bag7",
+            RandomizerKind::Bag14 => "bag14",
+            RandomizerKind::History => "history",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RandomizerKind> {
+        RandomizerKind::ALL
+            .into_iter()
+            .find(|kind| kind.get_name() == name)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Randomizer {
+    kind: RandomizerKind,
+    random: Random,
+    // blocks left in the current bag, dealt from the end
+    bag: Vec<i32>,
+    // most recently dealt blocks, newest last
+    history: Vec<i32>,
+}
+
+impl Randomizer {
+    pub const fn new(kind: RandomizerKind, seed: u64) -> Self {
+        Self {
+            kind,
+            random: Random::new(seed),
+            bag: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn get_kind(&self) -> RandomizerKind {
+        self.kind
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.random.get_seed()
+    }
+
+    // index of the next block as understood by `blocks::new`
+    pub fn next_block_index(&mut self) -> i32 {
+        match self.kind {
+            RandomizerKind::Uniform => self.next_uniform(),
+            RandomizerKind::Bag7 => self.next_from_bag(1),
+            RandomizerKind::Bag14 => self.next_from_bag(2),
+            RandomizerKind::History => self.next_with_history(),
+        }
+    }
+
+    fn next_uniform(&mut self) -> i32 {
+        self.random.range(0, blocks::NUM_BLOCK_TYPES)
+    }
+
+    fn next_from_bag(&mut self, copies: i32) -> i32 {
+        if self.bag.is_empty() {
+            for _ in 0..copies {
+                self.bag.extend(0..blocks::NUM_BLOCK_TYPES);
+            }
+            // Fisher-Yates shuffle
+            for i in (1..self.bag.len()).rev() {
+                let j = self.random.range(0, i as i32 + 1) as usize;
+                self.bag.swap(i, j);
+            }
+        }
+        self.bag.pop().unwrap()
+    }
+
+    fn next_with_history(&mut self) -> i32 {
+        if self.history.is_empty() {
+            // start with the step blocks in the history, so the first block is never one of them
+            self.history = vec![3, 4, 3, 4];
+        }
+        let mut rand = self.next_uniform();
+        for _ in 1..HISTORY_RETRIES {
+            if !self.history.contains(&rand) {
+                break;
+            }
+            rand = self.next_uniform();
+        }
+        self.history.push(rand);
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+        rand
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::BlockType;
+    use crate::objects::Point;
+
+    const DRAWS: usize = 7000;
+
+    fn count_block_types(kind: RandomizerKind, seed: u64) -> Vec<(BlockType, usize)> {
+        let mut randomizer = Randomizer::new(kind, seed);
+        let mut counts: Vec<(BlockType, usize)> = Vec::new();
+        for _ in 0..DRAWS {
+            let block_type = blocks::new(randomizer.next_block_index(), 1, &Point { x: 5, y: 0 })
+                .get_block_type();
+            match counts.iter_mut().find(|(t, _)| *t == block_type) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((block_type, 1)),
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn every_block_type_is_reachable_with_every_randomizer() {
+        for kind in RandomizerKind::ALL {
+            for seed in [0, 1, 42, 4_294_967_295] {
+                let counts = count_block_types(kind, seed);
+                for block_type in BlockType::PLAYABLE {
+                    assert!(
+                        counts.iter().any(|(t, _)| *t == block_type),
+                        "{:?} never dealt by {:?} with seed {}",
+                        block_type,
+                        kind,
+                        seed
+                    );
+                }
+                assert_eq!(counts.len(), BlockType::PLAYABLE.len());
+            }
+        }
+    }
+
+    #[test]
+    fn block_types_are_evenly_distributed() {
+        let expected = DRAWS / BlockType::PLAYABLE.len();
+        for kind in RandomizerKind::ALL {
+            for (block_type, count) in count_block_types(kind, 7) {
+                assert!(
+                    count > expected * 85 / 100 && count < expected * 115 / 100,
+                    "{:?} dealt {} times by {:?}, expected about {}",
+                    block_type,
+                    count,
+                    kind,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bags_contain_every_block_type_the_same_number_of_times() {
+        for (kind, copies) in [(RandomizerKind::Bag7, 1), (RandomizerKind::Bag14, 2)] {
+            let mut randomizer = Randomizer::new(kind, 3);
+            for _ in 0..100 {
+                let mut bag: Vec<i32> = (0..blocks::NUM_BLOCK_TYPES * copies)
+                    .map(|_| randomizer.next_block_index())
+                    .collect();
+                bag.sort();
+                let expected: Vec<i32> = (0..blocks::NUM_BLOCK_TYPES)
+                    .flat_map(|index| std::iter::repeat_n(index, copies as usize))
+                    .collect();
+                assert_eq!(bag, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn history_randomizer_rarely_repeats() {
+        let mut randomizer = Randomizer::new(RandomizerKind::History, 11);
+        let mut previous = randomizer.next_block_index();
+        let mut repeats = 0;
+        for _ in 0..DRAWS {
+            let rand = randomizer.next_block_index();
+            if rand == previous {
+                repeats += 1;
+            }
+            previous = rand;
+        }
+        // a uniform randomizer repeats about every 7th block
+        assert!(repeats < DRAWS / 20, "{} repeats", repeats);
+    }
+
+    #[test]
+    fn same_seed_deals_same_blocks() {
+        for kind in RandomizerKind::ALL {
+            let mut first = Randomizer::new(kind, 99);
+            let mut second = Randomizer::new(kind, 99);
+            for _ in 0..100 {
+                assert_eq!(first.next_block_index(), second.next_block_index());
+            }
+        }
+    }
+
+    #[test]
+    fn randomizer_kind_names_round_trip() {
+        for kind in RandomizerKind::ALL {
+            assert_eq!(RandomizerKind::from_name(kind.get_name()), Some(kind));
+        }
+        assert_eq!(RandomizerKind::from_name("unknown"), None);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::blocks::Block;
+use crate::blocks::BlockType;
+use crate::game::GameConfig;
+use crate::objects::DrawState;
+use crate::objects::Point;
+use crate::t_spin::TSpin;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Presents the game to the player. The game calls it whenever something visible changes.
+pub trait Renderer {
+    // a new game starts on a board with the given dimensions
+    fn start(&mut self, config: &GameConfig);
+    // board with the landed pieces and the falling block
+    fn draw_frame(&mut self, draw_state: &DrawState);
+    // blocks coming up after the current one, next first, still at their spawn point
+    fn draw_preview(&mut self, next_blocks: &[Block]);
+    // block kept aside with hold, if any
+    fn draw_hold(&mut self, hold_block: Option<&Block>);
+    // the block that just locked was spun into place, clearing `rows` rows
+    fn draw_t_spin(&mut self, t_spin: TSpin, rows: usize);
+    fn draw_pause(&mut self);
+    fn draw_game_over(&mut self);
+}
+
+// one call to a `Renderer`, as seen by `RecordingRenderer`
+#[derive(Clone, PartialEq, Debug)]
+pub enum RenderCall {
+    Start {
+        width: i32,
+        height: i32,
+        hidden_rows: i32,
+    },
+    Frame {
+        current_block: BlockType,
+        current_pieces: Vec<Point>,
+        ghost_pieces: Option<Vec<Point>>,
+        // occupied cells of the block stack, row by row from the top
+        stack: Vec<Point>,
+        clearing_rows: Vec<i32>,
+    },
+    Preview {
+        next_blocks: Vec<BlockType>,
+    },
+    Hold {
+        hold_block: Option<BlockType>,
+    },
+    TSpin {
+        t_spin: TSpin,
+        rows: usize,
+    },
+    Pause,
+    GameOver,
+}
+
+// Renderer keeping every call in memory, to check in tests what was drawn.
+// Clones share the recorded calls, so a clone can be handed to the game and the original inspected.
+#[derive(Clone, Default)]
+pub struct RecordingRenderer {
+    calls: Rc<RefCell<Vec<RenderCall>>>,
+}
+
+impl RecordingRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_calls(&self) -> Vec<RenderCall> {
+        self.calls.borrow().clone()
+    }
+
+    // only the frames, leaving out preview, hold, pause and the like
+    pub fn get_frames(&self) -> Vec<RenderCall> {
+        self.calls
+            .borrow()
+            .iter()
+            .filter(|call| matches!(call, RenderCall::Frame { .. }))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.calls.borrow_mut().clear();
+    }
+
+    fn record(&self, call: RenderCall) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn start(&mut self, config: &GameConfig) {
+        self.record(RenderCall::Start {
+            width: config.width,
+            height: config.height,
+            hidden_rows: config.hidden_rows,
+        });
+    }
+
+    fn draw_frame(&mut self, draw_state: &DrawState) {
+        self.record(RenderCall::Frame {
+            current_block: draw_state.current_block.get_block_type(),
+            current_pieces: draw_state.current_block.get_pieces().clone(),
+            ghost_pieces: draw_state
+                .ghost_block
+                .map(|block| block.get_pieces().clone()),
+            stack: draw_state
+                .block_stack
+                .get_cells()
+                .map(|(point, _cell)| point)
+                .collect(),
+            clearing_rows: draw_state.clearing_rows.to_vec(),
+        });
+    }
+
+    fn draw_preview(&mut self, next_blocks: &[Block]) {
+        self.record(RenderCall::Preview {
+            next_blocks: next_blocks
+                .iter()
+                .map(|block| block.get_block_type())
+                .collect(),
+        });
+    }
+
+    fn draw_hold(&mut self, hold_block: Option<&Block>) {
+        self.record(RenderCall::Hold {
+            hold_block: hold_block.map(|block| block.get_block_type()),
+        });
+    }
+
+    fn draw_t_spin(&mut self, t_spin: TSpin, rows: usize) {
+        self.record(RenderCall::TSpin { t_spin, rows });
+    }
+
+    fn draw_pause(&mut self) {
+        self.record(RenderCall::Pause);
+    }
+
+    fn draw_game_over(&mut self) {
+        self.record(RenderCall::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::input::InputAction;
+
+    // more than the time between two gravity steps at any level
+    const STEP_MS: u32 = 1000;
+
+    fn start_game(config: GameConfig) -> (Game, RecordingRenderer) {
+        let renderer = RecordingRenderer::new();
+        let mut game = Game::default();
+        game.set_state("test", 1, 5, config, Box::new(renderer.clone()));
+        (game, renderer)
+    }
+
+    fn pieces_of(call: &RenderCall) -> Vec<Point> {
+        match call {
+            RenderCall::Frame { current_pieces, .. } => current_pieces.clone(),
+            _ => panic!("not a frame: {:?}", call),
+        }
+    }
+
+    #[test]
+    fn start_reports_board_dimensions() {
+        let config = GameConfig {
+            width: 10,
+            height: 20,
+            hidden_rows: 2,
+            ..GameConfig::default()
+        };
+        let (_game, renderer) = start_game(config);
+        assert_eq!(
+            renderer.get_calls(),
+            vec![RenderCall::Start {
+                width: 10,
+                height: 20,
+                hidden_rows: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn each_step_draws_a_frame_the_preview_and_the_hold() {
+        let (mut game, renderer) = start_game(GameConfig::default());
+        renderer.clear();
+        game.world_loop_contents(STEP_MS);
+        let calls = renderer.get_calls();
+        assert_eq!(calls.len(), 3);
+        assert!(matches!(calls[0], RenderCall::Frame { .. }));
+        assert!(matches!(calls[1], RenderCall::Preview { .. }));
+        assert_eq!(calls[2], RenderCall::Hold { hold_block: None });
+    }
+
+    #[test]
+    fn falling_block_moves_down_one_row_per_gravity_step() {
+        // level 1 moves the block down every 450 ms
+        const GRAVITY_STEP_MS: u32 = 500;
+        let (mut game, renderer) = start_game(GameConfig::default());
+        for step in 0..3 {
+            game.world_loop_contents(STEP_MS + step * GRAVITY_STEP_MS);
+        }
+        let frames = renderer.get_frames();
+        assert_eq!(frames.len(), 3);
+        for pair in frames.windows(2) {
+            let moved: Vec<Point> = pieces_of(&pair[0])
+                .iter()
+                .map(|point| Point {
+                    x: point.x,
+                    y: point.y + 1,
+                })
+                .collect();
+            assert_eq!(moved, pieces_of(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn pause_is_drawn_instead_of_frames() {
+        let (mut game, renderer) = start_game(GameConfig::default());
+        game.world_loop_contents(STEP_MS);
+        game.press(InputAction::Pause, STEP_MS + 1);
+        renderer.clear();
+        game.world_loop_contents(2 * STEP_MS);
+        game.world_loop_contents(3 * STEP_MS);
+        assert_eq!(
+            renderer.get_calls(),
+            vec![RenderCall::Pause, RenderCall::Pause]
+        );
+    }
+
+    #[test]
+    fn blocks_pile_up_until_game_over() {
+        let config = GameConfig {
+            width: 10,
+            height: 6,
+            ..GameConfig::default()
+        };
+        let (mut game, renderer) = start_game(config);
+        let mut step = 1;
+        while game.world_loop_contents(step * STEP_MS) {
+            step += 1;
+            assert!(step < 1000, "game did not end");
+        }
+        let calls = renderer.get_calls();
+        assert_eq!(calls.last(), Some(&RenderCall::GameOver));
+        match renderer.get_frames().last().unwrap() {
+            RenderCall::Frame { stack, .. } => assert!(!stack.is_empty()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn ghost_is_drawn_at_the_bottom_unless_disabled() {
+        let (mut game, renderer) = start_game(GameConfig::default());
+        game.world_loop_contents(STEP_MS);
+        match renderer.get_frames().last().unwrap() {
+            RenderCall::Frame {
+                current_pieces,
+                ghost_pieces: Some(ghost_pieces),
+                ..
+            } => {
+                let bottom = GameConfig::default().get_total_height() - 1;
+                assert_eq!(ghost_pieces.iter().map(|point| point.y).max(), Some(bottom));
+                for (current, ghost) in current_pieces.iter().zip(ghost_pieces.iter()) {
+                    assert_eq!(current.x, ghost.x);
+                }
+            }
+            call => panic!("no ghost in {:?}", call),
+        }
+
+        let config = GameConfig {
+            show_ghost: false,
+            ..GameConfig::default()
+        };
+        let (mut game, renderer) = start_game(config);
+        game.world_loop_contents(STEP_MS);
+        assert!(matches!(
+            renderer.get_frames().last(),
+            Some(RenderCall::Frame {
+                ghost_pieces: None,
+                ..
+            })
+        ));
+    }
+}
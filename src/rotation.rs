@@ -0,0 +1,227 @@
+use crate::blocks::BlockType;
+use serde::{Deserialize, Serialize};
+
+// cells of a block relative to the upper left corner of its bounding box, x to the right, y down
+type Shape = [(i32, i32); 4];
+
+// the four rotation states, clockwise, starting with the one in the guideline
+const SHAPES_LONG: [Shape; 4] = [
+    [(0, 1), (1, 1), (2, 1), (3, 1)],
+    [(2, 0), (2, 1), (2, 2), (2, 3)],
+    [(0, 2), (1, 2), (2, 2), (3, 2)],
+    [(1, 0), (1, 1), (1, 2), (1, 3)],
+];
+const SHAPES_TEE: [Shape; 4] = [
+    [(1, 0), (0, 1), (1, 1), (2, 1)],
+    [(1, 0), (1, 1), (2, 1), (1, 2)],
+    [(0, 1), (1, 1), (2, 1), (1, 2)],
+    [(1, 0), (0, 1), (1, 1), (1, 2)],
+];
+const SHAPES_QUAD: [Shape; 4] = [[(0, 0), (1, 0), (0, 1), (1, 1)]; 4];
+const SHAPES_STEP_LEFT: [Shape; 4] = [
+    [(0, 0), (1, 0), (1, 1), (2, 1)],
+    [(2, 0), (1, 1), (2, 1), (1, 2)],
+    [(0, 1), (1, 1), (1, 2), (2, 2)],
+    [(1, 0), (0, 1), (1, 1), (0, 2)],
+];
+const SHAPES_STEP_RIGHT: [Shape; 4] = [
+    [(1, 0), (2, 0), (0, 1), (1, 1)],
+    [(1, 0), (1, 1), (2, 1), (2, 2)],
+    [(1, 1), (2, 1), (0, 2), (1, 2)],
+    [(0, 0), (0, 1), (1, 1), (1, 2)],
+];
+const SHAPES_L_LEFT: [Shape; 4] = [
+    [(2, 0), (0, 1), (1, 1), (2, 1)],
+    [(1, 0), (1, 1), (1, 2), (2, 2)],
+    [(0, 1), (1, 1), (2, 1), (0, 2)],
+    [(0, 0), (1, 0), (1, 1), (1, 2)],
+];
+const SHAPES_L_RIGHT: [Shape; 4] = [
+    [(0, 0), (0, 1), (1, 1), (2, 1)],
+    [(1, 0), (2, 0), (1, 1), (1, 2)],
+    [(0, 1), (1, 1), (2, 1), (2, 2)],
+    [(1, 0), (1, 1), (0, 2), (1, 2)],
+];
+
+// Offsets tried in order when rotating, the first one where the block fits wins.
+// Written as in the guideline with y pointing up, `get_kicks` turns them around.
+// Indexed by the rotation state the block turns from, clockwise and counterclockwise.
+const KICKS_CLOCKWISE: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+const KICKS_COUNTERCLOCKWISE: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+const KICKS_LONG_CLOCKWISE: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+];
+const KICKS_LONG_COUNTERCLOCKWISE: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+];
+// turning by 180 degrees, the same for all blocks
+const KICKS_180: [[(i32, i32); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+const NO_KICKS: [(i32, i32); 1] = [(0, 0)];
+
+// the offset a successful rotation used, an index into `RotationSystem::get_kicks`
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Kick {
+    pub turns: i32,
+    pub index: usize,
+}
+
+// how a block behaves when it is turned
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RotationSystem {
+    // rotation states and wall kicks of the guideline's super rotation system
+    Srs,
+    // same rotation states, but a block that does not fit after turning stays as it is
+    Classic,
+}
+
+impl RotationSystem {
+    pub const ALL: [RotationSystem; 2] = [RotationSystem::Srs, RotationSystem::Classic];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            RotationSystem::Srs => "srs",
+            RotationSystem::Classic => "classic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RotationSystem> {
+        RotationSystem::ALL
+            .into_iter()
+            .find(|system| system.get_name() == name)
+    }
+
+    // offsets to try when turning a block from `rotation` by `turns` (1 clockwise, -1 counterclockwise, 2 half)
+    pub fn get_kicks(&self, block_type: BlockType, rotation: usize, turns: i32) -> Vec<(i32, i32)> {
+        let kicks: &[(i32, i32)] = match (self, block_type, turns) {
+            (RotationSystem::Classic, _, _) | (_, BlockType::Quad, _) => &NO_KICKS,
+            (RotationSystem::Srs, _, 2) => &KICKS_180[rotation],
+            (RotationSystem::Srs, BlockType::Long, 1) => &KICKS_LONG_CLOCKWISE[rotation],
+            (RotationSystem::Srs, BlockType::Long, _) => &KICKS_LONG_COUNTERCLOCKWISE[rotation],
+            (RotationSystem::Srs, _, 1) => &KICKS_CLOCKWISE[rotation],
+            (RotationSystem::Srs, _, _) => &KICKS_COUNTERCLOCKWISE[rotation],
+        };
+        kicks.iter().map(|(x, y)| (*x, -*y)).collect()
+    }
+}
+
+// cells of the block type in the given rotation state, relative to its bounding box
+pub fn get_shape(block_type: BlockType, rotation: usize) -> &'static [(i32, i32)] {
+    match block_type {
+        BlockType::DefaultBlock => &[],
+        BlockType::Long => &SHAPES_LONG[rotation],
+        BlockType::Tee => &SHAPES_TEE[rotation],
+        BlockType::Quad => &SHAPES_QUAD[rotation],
+        BlockType::StepLeft => &SHAPES_STEP_LEFT[rotation],
+        BlockType::StepRight => &SHAPES_STEP_RIGHT[rotation],
+        BlockType::LLeft => &SHAPES_L_LEFT[rotation],
+        BlockType::LRight => &SHAPES_L_RIGHT[rotation],
+    }
+}
+
+// blocks with a flat side spawn with it facing up
+pub fn get_spawn_rotation(block_type: BlockType) -> usize {
+    match block_type {
+        BlockType::Tee | BlockType::LLeft | BlockType::LRight => 2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_turn_around_their_bounding_box() {
+        for block_type in BlockType::PLAYABLE {
+            // a square box of the block's size, turning a cell clockwise maps (x, y) to (size - 1 - y, x)
+            let size = if block_type == BlockType::Long {
+                4
+            } else if block_type == BlockType::Quad {
+                2
+            } else {
+                3
+            };
+            for rotation in 0..4 {
+                let mut turned: Vec<(i32, i32)> = get_shape(block_type, rotation)
+                    .iter()
+                    .map(|(x, y)| (size - 1 - y, *x))
+                    .collect();
+                let mut expected = get_shape(block_type, (rotation + 1) % 4).to_vec();
+                turned.sort();
+                expected.sort();
+                assert_eq!(
+                    turned, expected,
+                    "{:?} from rotation {}",
+                    block_type, rotation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn kicks_of_opposite_turns_cancel_out() {
+        for block_type in BlockType::PLAYABLE {
+            for rotation in 0..4 {
+                let next = (rotation + 1) % 4;
+                let clockwise = RotationSystem::Srs.get_kicks(block_type, rotation, 1);
+                let back = RotationSystem::Srs.get_kicks(block_type, next, -1);
+                for (kick, kick_back) in clockwise.iter().zip(back.iter()) {
+                    assert_eq!((kick.0 + kick_back.0, kick.1 + kick_back.1), (0, 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn half_turn_kicks_of_opposite_states_mirror_each_other() {
+        for rotation in 0..4 {
+            let kicks = RotationSystem::Srs.get_kicks(BlockType::Tee, rotation, 2);
+            let back = RotationSystem::Srs.get_kicks(BlockType::Tee, (rotation + 2) % 4, 2);
+            assert_eq!(kicks[0], (0, 0));
+            if rotation % 2 == 0 {
+                for (kick, kick_back) in kicks.iter().zip(back.iter()) {
+                    assert_eq!((kick.0 + kick_back.0, kick.1 + kick_back.1), (0, 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn classic_rotation_does_not_kick() {
+        for block_type in BlockType::PLAYABLE {
+            assert_eq!(
+                RotationSystem::Classic.get_kicks(block_type, 0, 1),
+                vec![(0, 0)]
+            );
+        }
+    }
+
+    #[test]
+    fn rotation_system_names_round_trip() {
+        for system in RotationSystem::ALL {
+            assert_eq!(RotationSystem::from_name(system.get_name()), Some(system));
+        }
+        assert_eq!(RotationSystem::from_name("unknown"), None);
+    }
+}
@@ -0,0 +1,38 @@
+use crate::snapshot::GameSnapshot;
+use crate::utils::log;
+
+const STORAGE_KEY: &str = "savegame";
+
+fn storage_key(id_prefix: &str) -> String {
+    format!("{}{}", id_prefix, STORAGE_KEY)
+}
+
+// keeps the game in local storage, to continue it when the page is opened again
+pub fn store(id_prefix: &str, snapshot: &GameSnapshot) {
+    let window = web_sys::window().unwrap();
+    if let Some(local_storage) = window.local_storage().unwrap() {
+        let result = local_storage.set_item(&storage_key(id_prefix), &snapshot.to_json());
+        if let Err(error) = result {
+            log!(
+                "could not save game to local_storage: {}",
+                error.as_string().unwrap()
+            );
+        }
+    }
+}
+
+// the stored game, if any, removing it from local storage so it is continued only once
+pub fn take(id_prefix: &str) -> Option<GameSnapshot> {
+    let window = web_sys::window().unwrap();
+    let local_storage = window.local_storage().unwrap()?;
+    let key = storage_key(id_prefix);
+    let json = local_storage.get_item(&key).ok()??;
+    let _ = local_storage.remove_item(&key);
+    match GameSnapshot::from_json(&json) {
+        Ok(snapshot) => Some(snapshot),
+        Err(error) => {
+            log!("discarding saved game: {}", error);
+            None
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::gravity::GravityCurve;
+use crate::streaks::LockBonus;
+use crate::t_spin::TSpin;
+use serde::{Deserialize, Serialize};
+
+const ROWS_FOR_LEVEL_UP: i32 = 10;
+
+// what a locked block cleared, as far as scoring is concerned
+pub struct Clear {
+    pub rows: usize,
+    pub t_spin: Option<TSpin>,
+    pub bonus: LockBonus,
+}
+
+// Points and level progression of a ruleset.
+pub trait ScoringRules {
+    // points for a locked block at `level`, without the drop points
+    fn get_clear_points(&self, clear: &Clear, level: i32) -> i32;
+    fn get_soft_drop_points_per_row(&self) -> i32;
+    fn get_hard_drop_points_per_row(&self) -> i32;
+    // whether a game started at `start_level`, now at `level`, moves up after clearing `lines` rows in total
+    fn is_level_up(&self, start_level: i32, level: i32, lines: i32) -> bool;
+    // how fast blocks fall at each level
+    fn get_gravity_curve(&self) -> GravityCurve;
+}
+
+// the ruleset a game is played with, recorded with its highscore
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ScoringKind {
+    // the rules this game always had
+    Legacy,
+    // classic NES, only the rows cleared at once count
+    Nes,
+    // modern guideline with T-spins, combos, back-to-back and perfect clears
+    Guideline,
+}
+
+impl ScoringKind {
+    pub const ALL: [ScoringKind; 3] = [
+        ScoringKind::Legacy,
+        ScoringKind::Nes,
+        ScoringKind::Guideline,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ScoringKind::Legacy => "legacy",
+            ScoringKind::Nes => "nes",
+            ScoringKind::Guideline => "guideline",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ScoringKind> {
+        ScoringKind::ALL
+            .into_iter()
+            .find(|kind| kind.get_name() == name)
+    }
+
+    pub fn get_rules(&self) -> &'static dyn ScoringRules {
+        match self {
+            ScoringKind::Legacy => &LegacyScoring,
+            ScoringKind::Nes => &NesScoring,
+            ScoringKind::Guideline => &GuidelineScoring,
+        }
+    }
+}
+
+// per level, by the number of rows of the clear that emptied the stack
+const LEGACY_PERFECT_CLEAR_POINTS: [i32; 4] = [8, 12, 18, 20];
+
+// 1/3/4/8 times the level, plus T-spins and streaks on the same scale
+struct LegacyScoring;
+
+impl ScoringRules for LegacyScoring {
+    fn get_clear_points(&self, clear: &Clear, level: i32) -> i32 {
+        let mut points = match (clear.t_spin, clear.rows) {
+            (None, 1) => 1,
+            (None, 2) => 3,
+            (None, 3) => 4,
+            (None, 4) => 8,
+            (None, _) => 0,
+            // a spin counts even without clearing a row
+            (Some(TSpin::Mini), 0) => 1,
+            (Some(TSpin::Mini), 1) => 2,
+            (Some(TSpin::Mini), _) => 4,
+            (Some(TSpin::Full), 0) => 4,
+            (Some(TSpin::Full), 1) => 8,
+            (Some(TSpin::Full), 2) => 12,
+            (Some(TSpin::Full), _) => 16,
+        };
+        if clear.bonus.back_to_back {
+            points = points * 3 / 2;
+        }
+        if clear.bonus.combo > 1 {
+            points += clear.bonus.combo as i32 - 1;
+        }
+        if clear.bonus.perfect_clear {
+            points += LEGACY_PERFECT_CLEAR_POINTS[clear.rows.min(4) - 1];
+        }
+        points * level
+    }
+
+    fn get_soft_drop_points_per_row(&self) -> i32 {
+        1
+    }
+
+    fn get_hard_drop_points_per_row(&self) -> i32 {
+        1
+    }
+
+    // every 10 rows per level reached, regardless of the start level
+    fn is_level_up(&self, _start_level: i32, level: i32, lines: i32) -> bool {
+        lines >= level * ROWS_FOR_LEVEL_UP
+    }
+
+    fn get_gravity_curve(&self) -> GravityCurve {
+        GravityCurve::Legacy
+    }
+}
+
+// 40/100/300/1200 times the level plus one, no hard drop points
+struct NesScoring;
+
+impl ScoringRules for NesScoring {
+    fn get_clear_points(&self, clear: &Clear, level: i32) -> i32 {
+        let points = match clear.rows {
+            1 => 40,
+            2 => 100,
+            3 => 300,
+            4 => 1200,
+            _ => 0,
+        };
+        points * (level + 1)
+    }
+
+    fn get_soft_drop_points_per_row(&self) -> i32 {
+        1
+    }
+
+    fn get_hard_drop_points_per_row(&self) -> i32 {
+        0
+    }
+
+    // a higher start level has to clear more rows before the first level up, then every 10 rows
+    fn is_level_up(&self, start_level: i32, level: i32, lines: i32) -> bool {
+        let first = (start_level * ROWS_FOR_LEVEL_UP + ROWS_FOR_LEVEL_UP)
+            .min((start_level * ROWS_FOR_LEVEL_UP - 50).max(100));
+        lines >= first + (level - start_level) * ROWS_FOR_LEVEL_UP
+    }
+
+    fn get_gravity_curve(&self) -> GravityCurve {
+        GravityCurve::Nes
+    }
+}
+
+// per level, by the number of rows of the clear that emptied the stack
+const GUIDELINE_PERFECT_CLEAR_POINTS: [i32; 4] = [800, 1200, 1800, 2000];
+const GUIDELINE_COMBO_POINTS: i32 = 50;
+
+// 100/300/500/800 times the level, T-spins, back-to-back times 1.5, combos and perfect clears
+struct GuidelineScoring;
+
+impl ScoringRules for GuidelineScoring {
+    fn get_clear_points(&self, clear: &Clear, level: i32) -> i32 {
+        let mut points = match (clear.t_spin, clear.rows) {
+            (None, 1) => 100,
+            (None, 2) => 300,
+            (None, 3) => 500,
+            (None, 4) => 800,
+            (None, _) => 0,
+            (Some(TSpin::Mini), 0) => 100,
+            (Some(TSpin::Mini), 1) => 200,
+            (Some(TSpin::Mini), _) => 400,
+            (Some(TSpin::Full), 0) => 400,
+            (Some(TSpin::Full), 1) => 800,
+            (Some(TSpin::Full), 2) => 1200,
+            (Some(TSpin::Full), _) => 1600,
+        };
+        if clear.bonus.back_to_back {
+            points = points * 3 / 2;
+        }
+        if clear.bonus.combo > 1 {
+            points += (clear.bonus.combo as i32 - 1) * GUIDELINE_COMBO_POINTS;
+        }
+        if clear.bonus.perfect_clear {
+            points += GUIDELINE_PERFECT_CLEAR_POINTS[clear.rows.min(4) - 1];
+        }
+        // level 0 scores as level 1, like it falls, see `gravity::GUIDELINE`
+        points * level.max(1)
+    }
+
+    fn get_soft_drop_points_per_row(&self) -> i32 {
+        1
+    }
+
+    fn get_hard_drop_points_per_row(&self) -> i32 {
+        2
+    }
+
+    // fixed goal of 10 rows per level
+    fn is_level_up(&self, start_level: i32, level: i32, lines: i32) -> bool {
+        lines >= (level - start_level + 1) * ROWS_FOR_LEVEL_UP
+    }
+
+    fn get_gravity_curve(&self) -> GravityCurve {
+        GravityCurve::Guideline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(rows: usize, t_spin: Option<TSpin>) -> Clear {
+        Clear {
+            rows,
+            t_spin,
+            bonus: LockBonus {
+                combo: if rows > 0 { 1 } else { 0 },
+                back_to_back: false,
+                perfect_clear: false,
+            },
+        }
+    }
+
+    #[test]
+    fn nes_scores_rows_times_level_plus_one_and_ignores_spins() {
+        let rules = ScoringKind::Nes.get_rules();
+        assert_eq!(rules.get_clear_points(&clear(1, None), 0), 40);
+        assert_eq!(rules.get_clear_points(&clear(4, None), 9), 12000);
+        assert_eq!(rules.get_clear_points(&clear(0, Some(TSpin::Full)), 1), 0);
+        assert_eq!(rules.get_clear_points(&clear(2, Some(TSpin::Full)), 1), 200);
+    }
+
+    #[test]
+    fn nes_start_level_delays_the_first_level_up() {
+        let rules = ScoringKind::Nes.get_rules();
+        assert!(rules.is_level_up(0, 0, 10));
+        assert!(!rules.is_level_up(5, 5, 59));
+        assert!(rules.is_level_up(5, 5, 60));
+        assert!(!rules.is_level_up(9, 9, 99));
+        assert!(rules.is_level_up(9, 9, 100));
+        assert!(rules.is_level_up(9, 10, 110));
+    }
+
+    #[test]
+    fn guideline_adds_back_to_back_combo_and_perfect_clear() {
+        let rules = ScoringKind::Guideline.get_rules();
+        let mut quad = clear(4, None);
+        assert_eq!(rules.get_clear_points(&quad, 2), 1600);
+        quad.bonus = LockBonus {
+            combo: 3,
+            back_to_back: true,
+            perfect_clear: true,
+        };
+        assert_eq!(rules.get_clear_points(&quad, 1), 1200 + 100 + 2000);
+    }
+
+    #[test]
+    fn guideline_level_0_scores_like_level_1() {
+        let rules = ScoringKind::Guideline.get_rules();
+        assert_eq!(rules.get_clear_points(&clear(1, None), 0), 100);
+        assert_eq!(
+            rules.get_clear_points(&clear(2, Some(TSpin::Full)), 0),
+            rules.get_clear_points(&clear(2, Some(TSpin::Full)), 1)
+        );
+    }
+
+    #[test]
+    fn scoring_names_round_trip() {
+        for kind in ScoringKind::ALL {
+            assert_eq!(ScoringKind::from_name(kind.get_name()), Some(kind));
+        }
+        assert_eq!(ScoringKind::from_name("unknown"), None);
+    }
+}
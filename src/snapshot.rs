@@ -0,0 +1,339 @@
+use crate::block_stack::BlockStack;
+use crate::blocks::Block;
+use crate::game::GameConfig;
+use crate::game::Phase;
+use crate::game::MAX_ENTRY_DELAY_MS;
+use crate::game::MAX_LINE_CLEAR_DELAY_MS;
+use crate::game::TICKS_PER_SECOND;
+use crate::lock_delay::LockDelay;
+use crate::randomizer::Randomizer;
+use crate::rotation::Kick;
+use crate::streaks::Streaks;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// increase whenever the fields below change in a way older snapshots can't be read with
+pub const SNAPSHOT_VERSION: u32 = 14;
+
+/// Complete state of a game, to continue it later exactly where it was left.
+/// Created by `Game::save` and handed back to `Game::restore`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub(crate) version: u32,
+    pub(crate) name: String,
+    pub(crate) score: i32,
+    pub(crate) lines: i32,
+    pub(crate) level: i32,
+    pub(crate) start_level: i32,
+    pub(crate) config: GameConfig,
+    // includes the state of the random number generator, so the same blocks follow
+    pub(crate) randomizer: Randomizer,
+    pub(crate) phase: Phase,
+    pub(crate) current_block: Block,
+    pub(crate) next_blocks: Vec<Block>,
+    pub(crate) hold_block: Option<Block>,
+    pub(crate) hold_used: bool,
+    pub(crate) last_kick: Option<Kick>,
+    pub(crate) buffered_turns: i32,
+    pub(crate) buffered_hold: bool,
+    pub(crate) streaks: Streaks,
+    pub(crate) lock_delay: LockDelay,
+    pub(crate) block_stack: BlockStack,
+    pub(crate) pause: bool,
+    // game time, wall clock timestamps don't survive a page reload
+    pub(crate) tick_count: u64,
+    pub(crate) fall_progress: u32,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum SnapshotError {
+    // not a snapshot, or one that was tampered with
+    Invalid(String),
+    // written by a different version of the game
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Invalid(reason) => write!(f, "invalid snapshot: {}", reason),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} not supported, expected {}",
+                version, SNAPSHOT_VERSION
+            ),
+        }
+    }
+}
+
+// only the version, to check it before the rest of the snapshot is parsed
+#[derive(Deserialize)]
+struct SnapshotVersion {
+    version: u32,
+}
+
+impl GameSnapshot {
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    // e.g. to continue a game only when the player is ready for it
+    pub fn set_pause(&mut self, pause: bool) {
+        self.pause = pause;
+    }
+
+    // Rejects snapshots the game could not continue from, e.g. edited ones.
+    pub(crate) fn check(&self) -> Result<(), SnapshotError> {
+        let invalid = |reason: &str| Err(SnapshotError::Invalid(reason.to_string()));
+        if self.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(self.version));
+        }
+        if self.config != self.config.clone().validated() {
+            return invalid("board size out of range");
+        }
+        if self.level < 0 || self.start_level < 0 {
+            return invalid("negative level");
+        }
+        let block_stack = &self.block_stack;
+        if !block_stack.is_valid()
+            || block_stack.get_width() != self.config.width
+            || block_stack.get_height() != self.config.get_total_height()
+        {
+            return invalid("block stack does not match the board");
+        }
+        if self.next_blocks.len() != self.config.preview_count as usize {
+            return invalid("next queue does not match the preview count");
+        }
+        let playable = |block: &Block| block.is_valid() && !block.get_pieces().is_empty();
+        if !self.next_blocks.iter().all(playable) || !self.hold_block.iter().all(playable) {
+            return invalid("next or hold block is broken");
+        }
+        // only a falling block is in play
+        let current_ok = match self.phase {
+            Phase::Falling => {
+                playable(&self.current_block) && block_stack.fits(&self.current_block)
+            }
+            _ => self.current_block.is_valid() && self.current_block.get_pieces().is_empty(),
+        };
+        if !current_ok {
+            return invalid("current block is broken or off the board");
+        }
+        // a delay can't run longer than the longest one the config allows
+        let game_time = self.tick_count * 1000 / TICKS_PER_SECOND;
+        match &self.phase {
+            Phase::LineClear { start, end, rows } => {
+                if start >= end
+                    || *start > game_time
+                    || *end > game_time + MAX_LINE_CLEAR_DELAY_MS as u64
+                    || *rows != block_stack.get_complete_rows()
+                {
+                    return invalid("line clear does not match the game");
+                }
+            }
+            Phase::Entry { end } => {
+                if *end > game_time + MAX_ENTRY_DELAY_MS as u64 {
+                    return invalid("entry delay is too long");
+                }
+            }
+            Phase::Falling => {}
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let version: SnapshotVersion = serde_json::from_str(json)
+            .map_err(|error| SnapshotError::Invalid(error.to_string()))?;
+        if version.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version.version));
+        }
+        serde_json::from_str(json).map_err(|error| SnapshotError::Invalid(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks;
+    use crate::game::Game;
+    use crate::input::InputAction;
+    use crate::render::RecordingRenderer;
+    use crate::render::RenderCall;
+
+    const STEP_MS: u32 = 1000;
+
+    fn play(game: &mut Game, from_ms: u32, steps: u32) {
+        for step in 0..steps {
+            let timestamp = from_ms + step * STEP_MS;
+            game.press(InputAction::MoveLeft, timestamp + 1);
+            game.release(InputAction::MoveLeft, timestamp + 2);
+            game.world_loop_contents(timestamp + STEP_MS);
+        }
+    }
+
+    #[test]
+    fn restored_game_continues_exactly_like_the_original() {
+        let mut original = Game::default();
+        original.set_state(
+            "test",
+            3,
+            21,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        play(&mut original, 1, 40);
+
+        let json = original.save().to_json();
+        let mut restored = Game::default();
+        let renderer = RecordingRenderer::new();
+        restored
+            .restore(
+                GameSnapshot::from_json(&json).unwrap(),
+                Box::new(renderer.clone()),
+            )
+            .unwrap();
+        assert_eq!(restored.save().to_json(), json);
+
+        // the restored game runs on a different clock, e.g. after a page reload
+        play(&mut original, 40 * STEP_MS + 1, 40);
+        renderer.clear();
+        restored.world_loop_contents(7);
+        play(&mut restored, 7, 40);
+        assert_eq!(restored.save().to_json(), original.save().to_json());
+        assert!(matches!(
+            renderer.get_frames().last(),
+            Some(RenderCall::Frame { .. })
+        ));
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut game = Game::default();
+        game.set_state(
+            "test",
+            1,
+            1,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        let json = game.save().to_json().replacen(
+            &format!("\"version\":{}", SNAPSHOT_VERSION),
+            "\"version\":0",
+            1,
+        );
+        assert_eq!(
+            GameSnapshot::from_json(&json).err(),
+            Some(SnapshotError::UnsupportedVersion(0))
+        );
+        assert!(matches!(
+            GameSnapshot::from_json("{}"),
+            Err(SnapshotError::Invalid(_))
+        ));
+    }
+
+    // restores a snapshot of a new game after editing its JSON
+    fn restore_edited(edit: impl FnOnce(&mut serde_json::Value)) -> Result<(), SnapshotError> {
+        let mut game = Game::default();
+        game.set_state(
+            "test",
+            1,
+            1,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        let mut json = serde_json::to_value(game.save()).unwrap();
+        edit(&mut json);
+        let snapshot = serde_json::from_value(json).unwrap();
+        game.restore(snapshot, Box::new(RecordingRenderer::new()))
+    }
+
+    fn is_invalid(result: Result<(), SnapshotError>) -> bool {
+        matches!(result, Err(SnapshotError::Invalid(_)))
+    }
+
+    #[test]
+    fn blocks_with_impossible_rotations_or_pieces_are_rejected() {
+        assert!(is_invalid(restore_edited(|json| {
+            json["current_block"]["rotation"] = 9.into();
+        })));
+        assert!(is_invalid(restore_edited(|json| {
+            json["next_blocks"][0]["rotation"] = 4.into();
+        })));
+        assert!(is_invalid(restore_edited(|json| {
+            let mut hold_block = json["next_blocks"][0].clone();
+            hold_block["pieces"][0]["x"] = 40.into();
+            json["hold_block"] = hold_block;
+        })));
+        assert!(is_invalid(restore_edited(|json| {
+            json["current_block"]["position"]["x"] = (-20).into();
+        })));
+    }
+
+    #[test]
+    fn line_clear_must_lie_in_the_past_of_the_game() {
+        let line_clear = |start: u64, end: u64| {
+            restore_edited(move |json| {
+                json["current_block"] = serde_json::to_value(blocks::default_block()).unwrap();
+                json["tick_count"] = 6.into();
+                json["phase"] = serde_json::json!({
+                    "LineClear": { "start": start, "end": end, "rows": [] }
+                });
+            })
+        };
+        assert_eq!(line_clear(0, 200), Ok(()));
+        assert!(is_invalid(line_clear(200, 100)));
+        assert!(is_invalid(line_clear(150, 200)));
+        assert!(is_invalid(line_clear(
+            0,
+            100 + MAX_LINE_CLEAR_DELAY_MS as u64 + 1
+        )));
+    }
+
+    #[test]
+    fn entry_delay_longer_than_allowed_is_rejected() {
+        let entry = |end: u64| {
+            restore_edited(move |json| {
+                json["current_block"] = serde_json::to_value(blocks::default_block()).unwrap();
+                json["tick_count"] = 6.into();
+                json["phase"] = serde_json::json!({ "Entry": { "end": end } });
+            })
+        };
+        assert_eq!(entry(100 + MAX_ENTRY_DELAY_MS as u64), Ok(()));
+        assert!(is_invalid(entry(100 + MAX_ENTRY_DELAY_MS as u64 + 1)));
+    }
+
+    #[test]
+    fn row_fill_not_matching_the_cells_is_rejected() {
+        assert!(is_invalid(restore_edited(|json| {
+            json["block_stack"]["row_fill"][0] = 3.into();
+        })));
+    }
+
+    #[test]
+    fn negative_level_is_rejected() {
+        assert!(is_invalid(restore_edited(|json| {
+            json["level"] = (-1).into();
+        })));
+    }
+
+    #[test]
+    fn stack_not_matching_the_board_is_rejected() {
+        let mut game = Game::default();
+        game.set_state(
+            "test",
+            1,
+            1,
+            GameConfig::default(),
+            Box::new(RecordingRenderer::new()),
+        );
+        let mut snapshot = game.save();
+        snapshot.config.width -= 1;
+        assert!(matches!(
+            game.restore(snapshot, Box::new(RecordingRenderer::new())),
+            Err(SnapshotError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+// bonuses a single locked block earned, see `Streaks::lock`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LockBonus {
+    // consecutive clearing locks including this one, 0 if it cleared nothing
+    pub combo: u32,
+    // a difficult clear right after another one
+    pub back_to_back: bool,
+    // the clear left the stack empty
+    pub perfect_clear: bool,
+}
+
+// Runs of line clears that earn bonus points, and the best of them in the current game.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Streaks {
+    combo: u32,
+    // the last clear was a difficult one, locks without a clear don't break the chain
+    back_to_back: bool,
+    max_combo: u32,
+    back_to_backs: u32,
+    perfect_clears: u32,
+}
+
+impl Streaks {
+    pub const fn default() -> Self {
+        Self {
+            combo: 0,
+            back_to_back: false,
+            max_combo: 0,
+            back_to_backs: 0,
+            perfect_clears: 0,
+        }
+    }
+
+    // Called for every locked block. `difficult` for quads and T-spins that clear rows,
+    // `empty` if the stack is empty afterwards.
+    pub fn lock(&mut self, rows: usize, difficult: bool, empty: bool) -> LockBonus {
+        if rows == 0 {
+            self.combo = 0;
+            return LockBonus {
+                combo: 0,
+                back_to_back: false,
+                perfect_clear: false,
+            };
+        }
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+        let back_to_back = difficult && self.back_to_back;
+        if back_to_back {
+            self.back_to_backs += 1;
+        }
+        self.back_to_back = difficult;
+        if empty {
+            self.perfect_clears += 1;
+        }
+        LockBonus {
+            combo: self.combo,
+            back_to_back,
+            perfect_clear: empty,
+        }
+    }
+
+    pub fn get_max_combo(&self) -> u32 {
+        self.max_combo
+    }
+
+    pub fn get_back_to_backs(&self) -> u32 {
+        self.back_to_backs
+    }
+
+    pub fn get_perfect_clears(&self) -> u32 {
+        self.perfect_clears
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combo_counts_consecutive_clearing_locks() {
+        let mut streaks = Streaks::default();
+        assert_eq!(streaks.lock(1, false, false).combo, 1);
+        assert_eq!(streaks.lock(2, false, false).combo, 2);
+        assert_eq!(streaks.lock(1, false, false).combo, 3);
+        assert_eq!(streaks.lock(0, false, false).combo, 0);
+        assert_eq!(streaks.lock(1, false, false).combo, 1);
+        assert_eq!(streaks.get_max_combo(), 3);
+    }
+
+    #[test]
+    fn back_to_back_survives_locks_without_clears_but_not_easy_clears() {
+        let mut streaks = Streaks::default();
+        assert!(!streaks.lock(4, true, false).back_to_back);
+        streaks.lock(0, false, false);
+        assert!(streaks.lock(4, true, false).back_to_back);
+        streaks.lock(1, false, false);
+        assert!(!streaks.lock(4, true, false).back_to_back);
+        assert_eq!(streaks.get_back_to_backs(), 1);
+    }
+
+    #[test]
+    fn perfect_clear_needs_a_clear_that_empties_the_stack() {
+        let mut streaks = Streaks::default();
+        assert!(!streaks.lock(0, false, true).perfect_clear);
+        assert!(streaks.lock(2, false, true).perfect_clear);
+        assert_eq!(streaks.get_perfect_clears(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::block_stack::BlockStack;
+use crate::blocks::Block;
+use crate::blocks::BlockType;
+use crate::objects::Point;
+use crate::rotation::Kick;
+use serde::{Deserialize, Serialize};
+
+// corners of the 3x3 bounding box of a tee block, around its centre
+const CORNERS: [(i32, i32); 4] = [(0, 0), (2, 0), (0, 2), (2, 2)];
+// the two corners on the side the tee points to, by rotation state (up, right, down, left)
+const FRONT_CORNERS: [[(i32, i32); 2]; 4] = [
+    [(0, 0), (2, 0)],
+    [(2, 0), (2, 2)],
+    [(0, 2), (2, 2)],
+    [(0, 0), (0, 2)],
+];
+// the last kick of a quarter turn moves the block by two rows,
+// a spin using it counts as a full one even if a front corner is free
+const LAST_KICK: usize = 4;
+
+// a tee block locked after being turned into a tight spot
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TSpin {
+    // only one of the corners in front is blocked
+    Mini,
+    Full,
+}
+
+impl TSpin {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            TSpin::Mini => "T-spin mini",
+            TSpin::Full => "T-spin",
+        }
+    }
+}
+
+// Three corner rule, checked when a block locks.
+// `last_kick` is the kick the last rotation used, None if the block was moved after it.
+pub fn detect(block: &Block, block_stack: &BlockStack, last_kick: Option<Kick>) -> Option<TSpin> {
+    if block.get_block_type() != BlockType::Tee {
+        return None;
+    }
+    let last_kick = last_kick?;
+    let position = block.get_position();
+    // walls and the floor count as blocked
+    let blocked = |(x, y): &(i32, i32)| {
+        !block_stack.is_free(&Point {
+            x: position.x + x,
+            y: position.y + y,
+        })
+    };
+    if CORNERS.iter().filter(|corner| blocked(corner)).count() < 3 {
+        return None;
+    }
+    let front_blocked = FRONT_CORNERS[block.get_rotation()].iter().all(blocked);
+    // the kicks of a half turn are in a different order
+    let two_row_kick = last_kick.turns.abs() == 1 && last_kick.index == LAST_KICK;
+    if front_blocked || two_row_kick {
+        Some(TSpin::Full)
+    } else {
+        Some(TSpin::Mini)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks;
+
+    fn quarter_turn(index: usize) -> Option<Kick> {
+        Some(Kick { turns: 1, index })
+    }
+
+    // tee pointing down, with its bounding box in the lowest three rows of `rows`
+    fn tee_at_bottom(rows: &[&str], x: i32) -> (Block, BlockStack) {
+        let block_stack = BlockStack::from_rows(rows);
+        let block = blocks::new(1, 1, &Point { x: 0, y: 0 });
+        assert_eq!(block.get_rotation(), 2);
+        let position = block.get_position();
+        let block = block.get_moved(x - position.x, rows.len() as i32 - 3 - position.y);
+        assert!(block_stack.fits(&block));
+        (block, block_stack)
+    }
+
+    #[test]
+    fn three_blocked_corners_with_both_in_front_are_a_t_spin() {
+        let (block, block_stack) = tee_at_bottom(&["#....", "...##", "#.###"], 0);
+        assert_eq!(
+            detect(&block, &block_stack, quarter_turn(0)),
+            Some(TSpin::Full)
+        );
+        // moved after the rotation
+        assert_eq!(detect(&block, &block_stack, None), None);
+    }
+
+    #[test]
+    fn one_free_corner_in_front_is_a_mini_unless_the_last_kick_was_used() {
+        let (block, block_stack) = tee_at_bottom(&["#.#..", ".....", "#...."], 0);
+        assert_eq!(
+            detect(&block, &block_stack, quarter_turn(1)),
+            Some(TSpin::Mini)
+        );
+        assert_eq!(
+            detect(&block, &block_stack, quarter_turn(LAST_KICK)),
+            Some(TSpin::Full)
+        );
+        // the same index of a half turn is a one cell shift
+        let half_turn = Some(Kick {
+            turns: 2,
+            index: LAST_KICK,
+        });
+        assert_eq!(detect(&block, &block_stack, half_turn), Some(TSpin::Mini));
+    }
+
+    #[test]
+    fn two_blocked_corners_are_no_t_spin() {
+        let (block, block_stack) = tee_at_bottom(&["#.#..", ".....", "...##"], 0);
+        assert_eq!(detect(&block, &block_stack, quarter_turn(0)), None);
+        // other blocks never spin
+        let (_, block_stack) = tee_at_bottom(&["#.#..", ".....", "#...."], 0);
+        let block = blocks::new(6, 1, &Point { x: 1, y: 0 });
+        assert_eq!(detect(&block, &block_stack, quarter_turn(0)), None);
+    }
+}
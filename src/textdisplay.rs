@@ -6,29 +6,41 @@ fn document() -> Document {
     web_sys::window().unwrap().document().unwrap()
 }
 
-fn element(document: &Document, element_id: &str) -> web_sys::HtmlElement {
+fn element(document: &Document, id_prefix: &str, element_id: &str) -> web_sys::HtmlElement {
     document
-        .get_element_by_id(element_id)
+        .get_element_by_id(&format!("{}{}", id_prefix, element_id))
         .unwrap()
         .dyn_into::<web_sys::HtmlElement>()
         .unwrap()
 }
 
-pub fn update_text_display(score: i32, lines: i32, level: i32) {
+pub fn update_text_display(id_prefix: &str, score: i32, lines: i32, level: i32) {
     let document = document();
-    element(&document, "score").set_text_content(Some(&score.to_string()));
-    element(&document, "lines").set_text_content(Some(&lines.to_string()));
-    element(&document, "level").set_text_content(Some(&level.to_string()));
+    element(&document, id_prefix, "score").set_text_content(Some(&score.to_string()));
+    element(&document, id_prefix, "lines").set_text_content(Some(&lines.to_string()));
+    element(&document, id_prefix, "level").set_text_content(Some(&level.to_string()));
 }
 
-pub fn update_duration_display(duration: u32) {
+pub fn update_duration_display(id_prefix: &str, duration: u32) {
     let document = document();
-    element(&document, "duration").set_text_content(Some(&format_duration(duration)));
+    element(&document, id_prefix, "duration").set_text_content(Some(&format_duration(duration)));
 }
 
-pub fn set_background_colour(colour: &str) {
+pub fn update_seed_display(id_prefix: &str, seed: u64) {
     let document = document();
-    let element = element(&document, "body");
-    let style = element.style();
-    style.set_css_text(&("background-color: ".to_owned() + colour + ";"));
+    element(&document, id_prefix, "seed").set_text_content(Some(&seed.to_string()));
+}
+
+pub fn set_visible(id_prefix: &str, element_id: &str, visible: bool) {
+    let document = document();
+    let style = element(&document, id_prefix, element_id).style();
+    let display = if visible { "" } else { "none" };
+    style.set_property("display", display).unwrap();
+}
+
+// colours the board, each board on the page has its own level
+pub fn set_background_colour(id_prefix: &str, colour: &str) {
+    let document = document();
+    let style = element(&document, id_prefix, "board").style();
+    style.set_property("background-color", colour).unwrap();
 }
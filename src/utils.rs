@@ -1,5 +1,7 @@
+#[cfg(feature = "web")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "web")]
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
     // `set_panic_hook` function at least once during initialization, and then
@@ -11,27 +13,39 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 #[macro_export]
 macro_rules! log {
     ( $( $t:tt )* ) => {
         web_sys::console::log_1(&format!( $( $t )* ).into());
     }
 }
+
+// without a browser console there is nowhere to log to, arguments are still type checked
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+#[macro_export]
+macro_rules! log {
+    ( $( $t:tt )* ) => {
+        let _ = format_args!( $( $t )* );
+    }
+}
 pub(crate) use log;
 
+#[cfg(feature = "web")]
 pub fn handle_js_error(result: Result<(), JsValue>) {
-    if result.is_err() {
-        log!("{}", result.err().unwrap().as_string().unwrap());
+    if let Err(error) = result {
+        log!("{}", error.as_string().unwrap());
     }
 }
 
-pub fn random(min: i32, max: i32) -> i32 {
-    js_sys::Math::floor(js_sys::Math::random() * max as f64 - min as f64) as i32 + min
+#[cfg(feature = "web")]
+pub fn random_seed() -> u64 {
+    js_sys::Math::floor(js_sys::Math::random() * u32::MAX as f64) as u64
 }
 
 pub fn format_duration(duration: u32) -> String {
     let duration_in_secs = duration / 1000;
     let minutes = duration_in_secs / 60;
     let seconds = duration_in_secs % 60;
-    return format!("{:02}:{:02}", minutes, seconds);
+    format!("{:02}:{:02}", minutes, seconds)
 }
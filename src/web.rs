@@ -0,0 +1,643 @@
+use crate::colours;
+use crate::draw;
+use crate::game;
+use crate::game::Game;
+use crate::game::GameEvent;
+use crate::highscore;
+use crate::input::InputAction;
+use crate::lock_delay::LockReset;
+use crate::randomizer::RandomizerKind;
+use crate::rotation::RotationSystem;
+use crate::savegame;
+use crate::scoring::ScoringKind;
+use crate::snapshot::GameSnapshot;
+use crate::textdisplay;
+use crate::utils;
+use crate::utils::log;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(start)]
+fn start() -> Result<(), JsValue> {
+    utils::set_panic_hook();
+    log!("starting in rust");
+    Ok(())
+}
+
+// state of one board on the page, shared between the event callbacks and the render loop
+struct Board {
+    game: Game,
+    id_prefix: String,
+    // key code of the configurable key for turning by 180 degrees
+    rotate_180_key: u32,
+}
+
+type SharedBoard = Rc<RefCell<Board>>;
+
+impl Board {
+    fn element_id(&self, id: &str) -> String {
+        element_id(&self.id_prefix, id)
+    }
+
+    fn board_element(&self) -> web_sys::HtmlElement {
+        let document = web_sys::window().unwrap().document().unwrap();
+        document
+            .get_element_by_id(&self.element_id("board"))
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap()
+    }
+
+    // Keys go to the board that has the focus, keys pressed with the focus elsewhere are ignored.
+    fn has_focus(&self, e: &web_sys::KeyboardEvent) -> bool {
+        let target = e
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::Node>().ok());
+        self.board_element().contains(target.as_ref())
+    }
+
+    // so the keys reach this board without clicking it first
+    fn focus(&self) {
+        crate::utils::handle_js_error(self.board_element().focus());
+    }
+
+    // the configurable key takes precedence over the fixed mapping
+    fn action_for_key(&self, key_code: u32) -> Option<InputAction> {
+        if key_code == self.rotate_180_key {
+            Some(InputAction::Rotate180)
+        } else {
+            action_for_key(key_code)
+        }
+    }
+
+    // presents what happened inside the game since the last call
+    fn handle_game_events(&mut self) {
+        let id_prefix = &self.id_prefix;
+        for event in self.game.take_events() {
+            match event {
+                GameEvent::Started { seed } => {
+                    textdisplay::set_background_colour(id_prefix, "#FFF");
+                    textdisplay::update_seed_display(id_prefix, seed);
+                }
+                GameEvent::StatsChanged {
+                    score,
+                    lines,
+                    level,
+                } => textdisplay::update_text_display(id_prefix, score, lines, level),
+                GameEvent::LevelUp { level } => textdisplay::set_background_colour(
+                    id_prefix,
+                    colours::colours_for_level(level).colour_bg,
+                ),
+                GameEvent::DurationChanged { duration } => {
+                    textdisplay::update_duration_display(id_prefix, duration)
+                }
+                GameEvent::GameOver(result) => {
+                    let latest_timestamp = highscore::add_score(&result);
+                    highscore::print_highscores(id_prefix, latest_timestamp);
+                }
+            }
+        }
+    }
+}
+
+fn element_id(id_prefix: &str, id: &str) -> String {
+    format!("{}{}", id_prefix, id)
+}
+
+/// One game board on the page.
+/// All elements the board uses are looked up by their usual id with `id_prefix` prepended,
+/// so several boards can live side by side on one page.
+#[wasm_bindgen]
+pub struct GameHandle {
+    board: SharedBoard,
+}
+
+#[wasm_bindgen]
+impl GameHandle {
+    #[wasm_bindgen(constructor)]
+    pub fn new(id_prefix: &str) -> Result<GameHandle, JsValue> {
+        let board = Rc::new(RefCell::new(Board {
+            game: Game::default(),
+            id_prefix: id_prefix.to_string(),
+            rotate_180_key: DEFAULT_ROTATE_180_KEY,
+        }));
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        register_event_listeners(&document, &board)?;
+        highscore::print_highscores(id_prefix, None);
+
+        // continue the game that was running when the page was left, paused
+        if let Some(snapshot) = savegame::take(id_prefix) {
+            if let Err(error) = restore_game(&board, snapshot) {
+                log!(
+                    "could not continue saved game: {}",
+                    error.as_string().unwrap()
+                );
+            }
+        }
+
+        Ok(GameHandle { board })
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.borrow().game.is_over()
+    }
+
+    pub fn create_game(&self) {
+        create_game(&self.board);
+    }
+
+    /// Snapshot of the running game as JSON, `undefined` if no game is running.
+    pub fn save_game(&self) -> Option<String> {
+        let game = &self.board.borrow().game;
+        if game.is_over() {
+            None
+        } else {
+            Some(game.save().to_json())
+        }
+    }
+
+    /// Continues a game from a snapshot created by `save_game`, replacing the running one.
+    pub fn restore_game(&self, json: &str) -> Result<(), JsValue> {
+        let snapshot =
+            GameSnapshot::from_json(json).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        restore_game(&self.board, snapshot)
+    }
+}
+
+fn register_event_listeners(
+    document: &web_sys::Document,
+    board: &SharedBoard,
+) -> Result<(), JsValue> {
+    log!("register_event_listeners()");
+
+    register_event_listener_create(document, board)?;
+    register_event_listener_input_keyboard(document, board)?;
+    register_event_listeners_input_touch(document, board)?;
+    register_event_listener_page_hide(board)?;
+
+    Ok(())
+}
+
+// the running game is saved when the page is left, see `GameHandle::new`
+fn register_event_listener_page_hide(board: &SharedBoard) -> Result<(), JsValue> {
+    let board = board.clone();
+    let callback = Closure::wrap(Box::new(move |_e: web_sys::Event| {
+        let board = board.borrow();
+        if !board.game.is_over() {
+            let mut snapshot = board.game.save();
+            snapshot.set_pause(true);
+            savegame::store(&board.id_prefix, &snapshot);
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    web_sys::window()
+        .unwrap()
+        .add_event_listener_with_callback("pagehide", callback.as_ref().unchecked_ref())?;
+
+    callback.forget();
+
+    Ok(())
+}
+
+fn register_event_listener_create(
+    document: &web_sys::Document,
+    board: &SharedBoard,
+) -> Result<(), JsValue> {
+    let id = board.borrow().element_id("create");
+    let board = board.clone();
+    let callback = Closure::wrap(Box::new(move |_e: web_sys::Event| {
+        create_game(&board);
+    }) as Box<dyn FnMut(_)>);
+
+    document
+        .get_element_by_id(&id)
+        .unwrap()
+        .add_event_listener_with_callback("click", callback.as_ref().unchecked_ref())?;
+
+    callback.forget();
+
+    Ok(())
+}
+
+// z
+const DEFAULT_ROTATE_180_KEY: u32 = 0x5A;
+
+// keyboard mapping, None for keys the game does not use
+fn action_for_key(key_code: u32) -> Option<InputAction> {
+    match key_code {
+        0x41 => Some(InputAction::MoveLeft),
+        37 => Some(InputAction::MoveLeft), // left key
+        0x53 => Some(InputAction::SoftDrop),
+        40 => Some(InputAction::SoftDrop), // down key
+        0x57 => Some(InputAction::HardDrop),
+        38 => Some(InputAction::HardDrop), // up key
+        0x58 => Some(InputAction::SonicDrop),
+        0x43 => Some(InputAction::Hold),
+        0x44 => Some(InputAction::MoveRight),
+        39 => Some(InputAction::MoveRight), // right key
+        81 => Some(InputAction::RotateLeft),
+        0x45 => Some(InputAction::RotateRight),
+        80 => Some(InputAction::DebugInfo),
+        32 => Some(InputAction::Pause),
+        _ => None,
+    }
+}
+
+fn register_event_listener_input_keyboard(
+    document: &web_sys::Document,
+    board: &SharedBoard,
+) -> Result<(), JsValue> {
+    let board_keydown = board.clone();
+    let callback_keydown = Closure::wrap(Box::new(move |e: web_sys::KeyboardEvent| {
+        //log!("e.key_code(): {}", e.key_code());
+        let board = &mut *board_keydown.borrow_mut();
+        if !board.has_focus(&e) {
+            return;
+        }
+        let action = board.action_for_key(e.key_code());
+        let game = &mut board.game;
+        if !game.is_over() {
+            if let Some(action) = action {
+                e.prevent_default();
+                // held keys are repeated by the game itself, see `AutoShift`
+                if !e.repeat() {
+                    game.press(action, e.time_stamp() as u32);
+                }
+            }
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    let board_keyup = board.clone();
+    let callback_keyup = Closure::wrap(Box::new(move |e: web_sys::KeyboardEvent| {
+        let board = &mut *board_keyup.borrow_mut();
+        if !board.has_focus(&e) {
+            return;
+        }
+        let action = board.action_for_key(e.key_code());
+        let game = &mut board.game;
+        if !game.is_over() {
+            if let Some(action) = action {
+                e.prevent_default();
+                game.release(action, e.time_stamp() as u32);
+            }
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    // every board listens on the whole document, see `Board::has_focus`
+    document
+        .add_event_listener_with_callback("keydown", callback_keydown.as_ref().unchecked_ref())?;
+    document.add_event_listener_with_callback("keyup", callback_keyup.as_ref().unchecked_ref())?;
+
+    callback_keydown.forget();
+    callback_keyup.forget();
+
+    Ok(())
+}
+
+fn register_event_listeners_input_touch(
+    document: &web_sys::Document,
+    board: &SharedBoard,
+) -> Result<(), JsValue> {
+    register_event_listener_input_touch(
+        document,
+        board,
+        InputAction::RotateLeft,
+        "touch-turn-left",
+    )?;
+    register_event_listener_input_touch(
+        document,
+        board,
+        InputAction::RotateRight,
+        "touch-turn-right",
+    )?;
+    register_event_listener_input_touch(document, board, InputAction::Rotate180, "touch-turn-180")?;
+    register_event_listener_input_touch(document, board, InputAction::MoveLeft, "touch-left")?;
+    register_event_listener_input_touch_held(document, board, InputAction::SoftDrop, "touch-down")?;
+    register_event_listener_input_touch(
+        document,
+        board,
+        InputAction::SonicDrop,
+        "touch-sonic-drop",
+    )?;
+    register_event_listener_input_touch(document, board, InputAction::HardDrop, "touch-hard-drop")?;
+    register_event_listener_input_touch(document, board, InputAction::MoveRight, "touch-right")?;
+    register_event_listener_input_touch(document, board, InputAction::Pause, "touch-pause")?;
+    register_event_listener_input_touch(document, board, InputAction::Hold, "touch-hold")?;
+    Ok(())
+}
+
+// a click is a press immediately followed by a release
+fn register_event_listener_input_touch(
+    document: &web_sys::Document,
+    board: &SharedBoard,
+    action: InputAction,
+    id: &str,
+) -> Result<(), JsValue> {
+    let id = board.borrow().element_id(id);
+    let board = board.clone();
+    let callback = Closure::wrap(Box::new(move |e: web_sys::Event| {
+        let game = &mut board.borrow_mut().game;
+        if !game.is_over() {
+            let timestamp = e.time_stamp() as u32;
+            game.press(action, timestamp);
+            game.release(action, timestamp);
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    document
+        .get_element_by_id(&id)
+        .unwrap()
+        .add_event_listener_with_callback("click", callback.as_ref().unchecked_ref())?;
+
+    callback.forget();
+
+    Ok(())
+}
+
+// the action lasts as long as the button is touched or the mouse button is down on it
+fn register_event_listener_input_touch_held(
+    document: &web_sys::Document,
+    board: &SharedBoard,
+    action: InputAction,
+    id: &str,
+) -> Result<(), JsValue> {
+    let element = document
+        .get_element_by_id(&board.borrow().element_id(id))
+        .unwrap();
+    for (event_type, pressed) in [
+        ("pointerdown", true),
+        ("pointerup", false),
+        ("pointerleave", false),
+        ("pointercancel", false),
+    ] {
+        let board = board.clone();
+        let callback = Closure::wrap(Box::new(move |e: web_sys::Event| {
+            let game = &mut board.borrow_mut().game;
+            if !game.is_over() {
+                e.prevent_default();
+                let timestamp = e.time_stamp() as u32;
+                if pressed {
+                    game.press(action, timestamp);
+                } else {
+                    game.release(action, timestamp);
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+        element.add_event_listener_with_callback(event_type, callback.as_ref().unchecked_ref())?;
+        callback.forget();
+    }
+
+    Ok(())
+}
+
+fn create_game(board: &SharedBoard) {
+    if !board.borrow().game.is_over() {
+        return;
+    }
+    log!("creating game");
+    let document = web_sys::window().unwrap().document().unwrap();
+    let id_prefix = board.borrow().id_prefix.clone();
+
+    let name_element = document
+        .get_element_by_id(&element_id(&id_prefix, "name"))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap();
+    let seed_element = document
+        .get_element_by_id(&element_id(&id_prefix, "seed-input"))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap();
+    log!("  got parameter elements");
+
+    let name = name_element.value();
+    let start_level = parse_number_input(&document, &id_prefix, "start-level", 1);
+    // empty or invalid seed means a new random game
+    let seed = seed_element
+        .value()
+        .trim()
+        .parse()
+        .unwrap_or_else(|_| utils::random_seed());
+    let config = game::GameConfig {
+        randomizer: RandomizerKind::from_name(&select_value(&document, &id_prefix, "randomizer"))
+            .unwrap_or(RandomizerKind::Uniform),
+        rotation: RotationSystem::from_name(&select_value(&document, &id_prefix, "rotation"))
+            .unwrap_or(RotationSystem::Srs),
+        scoring: ScoringKind::from_name(&select_value(&document, &id_prefix, "scoring"))
+            .unwrap_or(ScoringKind::Legacy),
+        width: parse_number_input(
+            &document,
+            &id_prefix,
+            "board-width",
+            game::DEFAULT_BOARD_WIDTH,
+        ),
+        height: parse_number_input(
+            &document,
+            &id_prefix,
+            "board-height",
+            game::DEFAULT_BOARD_HEIGHT,
+        ),
+        hidden_rows: parse_number_input(&document, &id_prefix, "hidden-rows", 0),
+        preview_count: parse_number_input(
+            &document,
+            &id_prefix,
+            "preview-count",
+            game::DEFAULT_PREVIEW_COUNT,
+        ),
+        show_ghost: checkbox_value(&document, &id_prefix, "show-ghost"),
+        lock_delay: parse_number_input(
+            &document,
+            &id_prefix,
+            "lock-delay",
+            game::DEFAULT_LOCK_DELAY_MS as i32,
+        )
+        .max(0) as u32,
+        lock_reset: LockReset::from_name(&select_value(&document, &id_prefix, "lock-reset"))
+            .unwrap_or(LockReset::Move),
+        max_lock_resets: game::DEFAULT_MAX_LOCK_RESETS,
+        das: parse_number_input(&document, &id_prefix, "das", game::DEFAULT_DAS_MS as i32).max(0)
+            as u32,
+        arr: parse_number_input(&document, &id_prefix, "arr", game::DEFAULT_ARR_MS as i32).max(0)
+            as u32,
+        soft_drop_factor: parse_number_input(
+            &document,
+            &id_prefix,
+            "soft-drop-factor",
+            game::DEFAULT_SOFT_DROP_FACTOR as i32,
+        )
+        .max(0) as u32,
+        entry_delay: parse_number_input(&document, &id_prefix, "entry-delay", 0).max(0) as u32,
+        line_clear_delay: parse_number_input(&document, &id_prefix, "line-clear-delay", 0).max(0)
+            as u32,
+    };
+    log!("  got parameter values");
+
+    let rotate_180_key =
+        key_code_input(&document, &id_prefix, "rotate-180-key").unwrap_or(DEFAULT_ROTATE_180_KEY);
+    textdisplay::set_visible(
+        &id_prefix,
+        "touch-turn-180",
+        checkbox_value(&document, &id_prefix, "touch-turn-180-enabled"),
+    );
+
+    let renderer = create_renderer(&document, &id_prefix);
+
+    {
+        let mut board = board.borrow_mut();
+        board.rotate_180_key = rotate_180_key;
+        board
+            .game
+            .set_state(&name, start_level, seed, config, Box::new(renderer));
+        board.handle_game_events();
+        board.focus();
+    }
+
+    start_world_loop(board.clone());
+}
+
+fn restore_game(board: &SharedBoard, snapshot: GameSnapshot) -> Result<(), JsValue> {
+    let was_over = board.borrow().game.is_over();
+    log!("restoring game");
+    let document = web_sys::window().unwrap().document().unwrap();
+    let renderer = create_renderer(&document, &board.borrow().id_prefix);
+    {
+        let mut board = board.borrow_mut();
+        board
+            .game
+            .restore(snapshot, Box::new(renderer))
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        board.handle_game_events();
+    }
+    board.borrow().focus();
+    // a running game already has its loop
+    if was_over {
+        start_world_loop(board.clone());
+    }
+    Ok(())
+}
+
+fn create_renderer(document: &web_sys::Document, id_prefix: &str) -> draw::Draw {
+    let context = canvas_context(document, id_prefix, "canvas");
+    let context_next = canvas_context(document, id_prefix, "preview");
+    let context_hold = canvas_context(document, id_prefix, "hold");
+    log!("  got canvas context");
+
+    draw::Draw::create(context, context_next, context_hold)
+}
+
+fn canvas_context(
+    document: &web_sys::Document,
+    id_prefix: &str,
+    id: &str,
+) -> web_sys::CanvasRenderingContext2d {
+    document
+        .get_element_by_id(&element_id(id_prefix, id))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .unwrap()
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()
+        .unwrap()
+}
+
+fn select_value(document: &web_sys::Document, id_prefix: &str, id: &str) -> String {
+    document
+        .get_element_by_id(&element_id(id_prefix, id))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlSelectElement>()
+        .unwrap()
+        .value()
+}
+
+fn checkbox_value(document: &web_sys::Document, id_prefix: &str, id: &str) -> bool {
+    document
+        .get_element_by_id(&element_id(id_prefix, id))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap()
+        .checked()
+}
+
+// key code of the letter or digit typed into a text input, None if it is empty or something else
+fn key_code_input(document: &web_sys::Document, id_prefix: &str, id: &str) -> Option<u32> {
+    let value = document
+        .get_element_by_id(&element_id(id_prefix, id))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap()
+        .value();
+    let key = value.trim().chars().next()?.to_ascii_uppercase();
+    if key.is_ascii_alphanumeric() {
+        Some(key as u32)
+    } else {
+        None
+    }
+}
+
+fn parse_number_input(
+    document: &web_sys::Document,
+    id_prefix: &str,
+    id: &str,
+    default: i32,
+) -> i32 {
+    document
+        .get_element_by_id(&element_id(id_prefix, id))
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap()
+        .value()
+        .trim()
+        .parse()
+        .unwrap_or(default)
+}
+
+struct RenderLoop {
+    animation_id: Option<i32>,
+    closure: Option<Closure<dyn FnMut(u32)>>,
+}
+
+const EXPECT_MSG: &str = "cannot request animation frame";
+
+fn start_world_loop(board: SharedBoard) {
+    // see https://users.rust-lang.org/t/wasm-web-sys-how-to-use-window-request-animation-frame-resolved/20882
+    let render_loop: Rc<RefCell<RenderLoop>> = Rc::new(RefCell::new(RenderLoop {
+        animation_id: None,
+        closure: None,
+    }));
+    {
+        let closure: Closure<dyn FnMut(u32)> = {
+            let render_loop = render_loop.clone();
+            Closure::wrap(Box::new(move |timestamp: u32| {
+                {
+                    let mut board = board.borrow_mut();
+                    let continue_rendering = board.game.world_loop_contents(timestamp);
+                    board.handle_game_events();
+                    if !continue_rendering {
+                        return;
+                    }
+                }
+
+                let window = web_sys::window().unwrap();
+                let mut render_loop = render_loop.borrow_mut();
+                render_loop.animation_id = render_loop.closure.as_ref().map(|closure| {
+                    window
+                        .request_animation_frame(closure.as_ref().unchecked_ref())
+                        .expect(EXPECT_MSG)
+                });
+            }))
+        };
+        let window = web_sys::window().unwrap();
+        let mut render_loop = render_loop.borrow_mut();
+        render_loop.animation_id = Some(
+            window
+                .request_animation_frame(closure.as_ref().unchecked_ref())
+                .expect(EXPECT_MSG),
+        );
+        render_loop.closure = Some(closure);
+    }
+}